@@ -1,17 +1,34 @@
+use crate::{
+    column::Column,
+    config::Config,
+    crawler::{crawl_book_ids, to_absolute_url, KOBO_ORIGIN},
+    date::parse_release_date,
+    filter::{BookFilter, BookKind},
+    isbn::{Identifier, Isbn},
+    language::Language,
+    synopsis::{format_synopsis, SynopsisFormat},
+    tag::{join_tag_paths, process_tag_paths},
+};
 use anyhow::Result;
+use chrono::NaiveDate;
+use ego_tree::NodeId;
 use indicatif::ProgressBar;
 use scraper::{Html, Selector};
+use serde_json::{json, Value};
 use std::{
     fs::{create_dir, OpenOptions},
     io::Write,
     path::Path,
 };
 
-const BOOK_PATH: &str = "https://www.kobo.com/tw/zh/ebook/";
+const STORE_PATH: &str = "https://www.kobo.com/tw/zh";
 const IMG_DIR: &str = "./img";
-const CSV_FILE_PATH: &str = "./metadata.csv";
+/// Progress steps taken by scraping a book, before it is written
+pub const SCRAPE_STEPS: u64 = 28;
+/// Progress steps taken by writing a book in one output format
+pub const WRITE_STEPS: u64 = 2;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Metadata {
     id: String,
     title: String,
@@ -21,107 +38,199 @@ pub struct Metadata {
     series_index: Option<f64>,
     cover: String,
     synopsis: String,
-    tags: String,
+    tag_paths: Vec<Vec<String>>,
     publisher: String,
-    release_date: String,
+    release_date: Option<NaiveDate>,
     language_code: String,
-    isbn: String,
+    language_tag: String,
+    isbn: Option<Isbn>,
+    product_id: Option<String>,
+    page_count: Option<u32>,
+    word_count: Option<u32>,
+    reading_time: Option<f64>,
+    rating: Option<f64>,
+    rating_count: Option<u32>,
+    review_count: Option<u32>,
+    price: Option<f64>,
+    sale_price: Option<f64>,
+    currency: Option<String>,
+    file_format: Option<String>,
+    drm: Option<String>,
+    age_rating: Option<String>,
+    kind: BookKind,
+    narrators: String,
+    duration: Option<u32>,
+    abridged: Option<bool>,
 }
 
 impl Metadata {
-    pub fn append_to_csv_file(self, pb: &ProgressBar) -> Result<()> {
-        if !Path::new(IMG_DIR).exists() {
-            create_dir(IMG_DIR)?;
-        }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
 
-        let mut img_name = 1;
-        let mut img_path;
-        loop {
-            img_path = format!("{}/{}.jpg", IMG_DIR, img_name);
-            if !Path::new(&img_path).exists() {
-                break;
-            }
-            img_name += 1;
-        }
+    /// The ID with its kind, e.g. `audiobook/mistborn-1`, unique across ebooks and
+    /// audiobooks that share a Kobo ID
+    pub fn book_id(&self) -> String {
+        self.kind.to_book_id(&self.id)
+    }
 
-        let mut img_file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&img_path)?;
-        let img_response = reqwest::blocking::get(self.cover)?;
+    pub fn title(&self) -> &str {
+        &self.title
+    }
 
-        let img = img_response.bytes()?;
-        img_file.write_all(&img)?;
-        pb.inc(1);
+    pub fn subtitle(&self) -> Option<&str> {
+        self.subtitle.as_deref()
+    }
 
-        if !Path::new(CSV_FILE_PATH).exists() {
-            let mut csv_wtr = csv::Writer::from_path(CSV_FILE_PATH)?;
-            csv_wtr.write_record([
-                "ID",
-                "Title",
-                "Subtitle",
-                "Author(s)",
-                "Series",
-                "Series Index",
-                "Cover Path",
-                "Synopsis (HTML)",
-                "Tag(s)",
-                "Publisher",
-                "Release Date (yyyy-m-d)",
-                "Language Code (ISO 639-1)",
-                "ISBN",
-            ])?;
-        }
+    pub fn authors(&self) -> Vec<&str> {
+        self.authors
+            .split('&')
+            .filter(|author| !author.is_empty())
+            .collect()
+    }
 
-        let csv_file = OpenOptions::new().append(true).open(CSV_FILE_PATH)?;
-        let mut csv_wtr = csv::Writer::from_writer(csv_file);
-        csv_wtr.write_record([
-            self.id,
-            self.title,
-            self.subtitle.unwrap_or_default(),
-            self.authors,
-            self.series_name.unwrap_or_default(),
-            self.series_index
-                .map(|index| index.to_string())
-                .unwrap_or_default(),
-            img_path,
-            self.synopsis,
-            self.tags,
-            self.publisher,
-            self.release_date,
-            self.language_code,
-            self.isbn,
-        ])?;
-        pb.inc(1);
+    pub fn narrators(&self) -> Vec<&str> {
+        self.narrators
+            .split('&')
+            .filter(|narrator| !narrator.is_empty())
+            .collect()
+    }
 
-        Ok(())
+    pub fn series_name(&self) -> Option<&str> {
+        self.series_name.as_deref()
     }
-}
 
-pub trait Id {
-    fn from(self) -> Option<String>;
-    fn get_metadata(self, pb: &ProgressBar) -> Result<Metadata>;
-    fn get_book_page(self) -> Result<Html>;
-}
+    pub fn series_index(&self) -> Option<f64> {
+        self.series_index
+    }
 
-impl Id for &str {
-    fn from(self) -> Option<String> {
-        let is_not_kobo_book_url = !self.contains(BOOK_PATH);
-        if is_not_kobo_book_url {
-            return None;
-        }
+    pub fn synopsis(&self, format: SynopsisFormat) -> String {
+        format_synopsis(&self.synopsis, format)
+    }
 
-        let book_id = self
-            .rsplit_once('/')
-            .map(|(_, substring)| substring.trim().to_string())
-            .filter(|id| !id.is_empty())?;
+    pub fn publisher(&self) -> &str {
+        &self.publisher
+    }
 
-        Some(book_id)
+    pub fn release_date(&self) -> Option<NaiveDate> {
+        self.release_date
     }
 
-    fn get_metadata(self, pb: &ProgressBar) -> Result<Metadata> {
-        let book_page = self.get_book_page()?;
-        pb.inc(1);
+    pub fn isbn(&self) -> Option<&Isbn> {
+        self.isbn.as_ref()
+    }
+
+    pub fn language_code(&self) -> &str {
+        &self.language_code
+    }
+
+    pub fn language_tag(&self) -> &str {
+        &self.language_tag
+    }
+
+    pub fn product_id(&self) -> Option<&str> {
+        self.product_id.as_deref()
+    }
+
+    pub fn page_count(&self) -> Option<u32> {
+        self.page_count
+    }
+
+    pub fn word_count(&self) -> Option<u32> {
+        self.word_count
+    }
+
+    /// Estimated reading time, in hours
+    pub fn reading_time(&self) -> Option<f64> {
+        self.reading_time
+    }
+
+    pub fn rating(&self) -> Option<f64> {
+        self.rating
+    }
+
+    pub fn rating_count(&self) -> Option<u32> {
+        self.rating_count
+    }
+
+    pub fn review_count(&self) -> Option<u32> {
+        self.review_count
+    }
+
+    pub fn price(&self) -> Option<f64> {
+        self.price
+    }
+
+    pub fn sale_price(&self) -> Option<f64> {
+        self.sale_price
+    }
+
+    pub fn currency(&self) -> Option<&str> {
+        self.currency.as_deref()
+    }
+
+    pub fn file_format(&self) -> Option<&str> {
+        self.file_format.as_deref()
+    }
+
+    pub fn drm(&self) -> Option<&str> {
+        self.drm.as_deref()
+    }
+
+    pub fn age_rating(&self) -> Option<&str> {
+        self.age_rating.as_deref()
+    }
+
+    pub fn cover_url(&self) -> &str {
+        &self.cover
+    }
+
+    /// Running time of an audiobook, in minutes
+    pub fn duration(&self) -> Option<u32> {
+        self.duration
+    }
+
+    pub fn abridged(&self) -> Option<bool> {
+        self.abridged
+    }
+
+    pub fn kind(&self) -> BookKind {
+        self.kind
+    }
+
+    /// Kobo store page of the book
+    pub fn url(&self) -> String {
+        format!("{}{}{}", STORE_PATH, self.kind.path_segment(), self.id)
+    }
+
+    pub fn has_isbn(&self, isbn: &Isbn) -> bool {
+        self.isbn
+            .as_ref()
+            .is_some_and(|own_isbn| own_isbn.isbn_13 == isbn.isbn_13)
+    }
+
+    /// One-line summary used to pick a book among search results
+    pub fn describe(&self) -> String {
+        let authors = self.authors().join(", ");
+        let subtitle = self
+            .subtitle
+            .as_ref()
+            .map(|subtitle| format!(": {}", subtitle))
+            .unwrap_or_default();
+
+        format!(
+            "{}{} — {} ({})\n    {}",
+            self.title, subtitle, authors, self.publisher, self.cover
+        )
+    }
+
+    pub fn matches(&self, filter: &BookFilter) -> bool {
+        filter.matches(&self.language_code, &self.language_tag, self.release_date)
+    }
+
+    /// Scrapes a book page, advancing `pb` by every step but the download of the page
+    fn from_book_page(book_id: &str, book_page: &Html, pb: &ProgressBar) -> Self {
+        let (kind, id) = BookKind::split_book_id(book_id);
 
         let title = book_page.get_title();
         pb.inc(1);
@@ -129,7 +238,16 @@ impl Id for &str {
         let subtitle = book_page.get_subtitle();
         pb.inc(1);
 
-        let authors = book_page.get_authors_str();
+        let narrators = book_page.get_narrators_str();
+        pb.inc(1);
+
+        // Audiobook pages list narrators among the contributors too
+        let authors = book_page
+            .get_authors_str()
+            .split('&')
+            .filter(|author| !narrators.split('&').any(|narrator| narrator == *author))
+            .collect::<Vec<&str>>()
+            .join("&");
         pb.inc(1);
 
         let series_name = book_page.get_series_name();
@@ -144,7 +262,7 @@ impl Id for &str {
         let synopsis = book_page.get_synopsis_html();
         pb.inc(1);
 
-        let tags = book_page.get_tags_str();
+        let tag_paths = book_page.get_tag_paths();
         pb.inc(1);
 
         let publisher = book_page.get_publisher();
@@ -153,14 +271,67 @@ impl Id for &str {
         let release_date = book_page.get_release_date();
         pb.inc(1);
 
-        let language_code = book_page.get_language_code();
+        let language = book_page.get_language().unwrap_or_default();
+        pb.inc(1);
+
+        let identifier = book_page.get_identifier();
+        if let Some(Identifier::Invalid(value)) = &identifier {
+            pb.println(format!(
+                "Warning: {} has an invalid ISBN \"{}\"",
+                book_id, value
+            ));
+        }
+        let (isbn, product_id) = match identifier {
+            Some(Identifier::Isbn(isbn)) => (Some(isbn), None),
+            Some(Identifier::ProductId(product_id)) => (None, Some(product_id)),
+            _ => (None, None),
+        };
+        pb.inc(1);
+
+        let page_count = book_page.get_page_count();
+        pb.inc(1);
+
+        let word_count = book_page.get_word_count();
+        pb.inc(1);
+
+        let reading_time = book_page.get_reading_time();
+        pb.inc(1);
+
+        let rating = book_page.get_rating();
+        pb.inc(1);
+
+        let rating_count = book_page.get_rating_count();
+        pb.inc(1);
+
+        let review_count = book_page.get_review_count();
+        pb.inc(1);
+
+        let price = book_page.get_price();
         pb.inc(1);
 
-        let isbn = book_page.get_isbn();
+        let sale_price = book_page.get_sale_price();
         pb.inc(1);
 
-        Ok(Metadata {
-            id: self.to_string(),
+        let currency = book_page.get_currency();
+        pb.inc(1);
+
+        let file_format = book_page.get_file_format();
+        pb.inc(1);
+
+        let drm = book_page.get_drm();
+        pb.inc(1);
+
+        let age_rating = book_page.get_age_rating();
+        pb.inc(1);
+
+        let duration = book_page.get_duration();
+        pb.inc(1);
+
+        let abridged = book_page.get_abridged();
+        pb.inc(1);
+
+        Metadata {
+            id: id.to_string(),
             title,
             subtitle,
             authors,
@@ -168,36 +339,261 @@ impl Id for &str {
             series_index,
             cover,
             synopsis,
-            tags,
+            tag_paths,
             publisher,
             release_date,
-            language_code,
+            language_code: language.code,
+            language_tag: language.tag,
             isbn,
+            product_id,
+            page_count,
+            word_count,
+            reading_time,
+            rating,
+            rating_count,
+            review_count,
+            price,
+            sale_price,
+            currency,
+            file_format,
+            drm,
+            age_rating,
+            kind,
+            narrators,
+            duration,
+            abridged,
+        }
+    }
+
+    /// Downloads the cover to the next free path in ./img
+    pub fn download_cover(&self) -> Result<String> {
+        if !Path::new(IMG_DIR).exists() {
+            create_dir(IMG_DIR)?;
+        }
+
+        let mut img_name = 1;
+        let mut img_path;
+        loop {
+            img_path = format!("{}/{}.jpg", IMG_DIR, img_name);
+            if !Path::new(&img_path).exists() {
+                break;
+            }
+            img_name += 1;
+        }
+
+        self.download_cover_to(Path::new(&img_path))?;
+
+        Ok(img_path)
+    }
+
+    fn download_cover_to(&self, img_path: &Path) -> Result<()> {
+        let mut img_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(img_path)?;
+        img_file.write_all(&self.fetch_cover()?)?;
+
+        Ok(())
+    }
+
+    pub fn fetch_cover(&self) -> Result<Vec<u8>> {
+        let img_response = reqwest::blocking::get(&self.cover)?.error_for_status()?;
+        let img = img_response.bytes()?;
+
+        Ok(img.to_vec())
+    }
+
+    pub fn get_tag_paths(&self, config: &Config) -> Vec<Vec<String>> {
+        process_tag_paths(&self.tag_paths, config.tag_map.as_ref(), config.leaf_tags)
+    }
+
+    pub fn to_json(&self, img_path: &str, config: &Config) -> Value {
+        json!({
+            "id": self.id,
+            "title": self.title,
+            "subtitle": self.subtitle,
+            "authors": self.authors(),
+            "series_name": self.series_name,
+            "series_index": self.series_index,
+            "cover_path": img_path,
+            "cover_url": self.cover,
+            "synopsis": format_synopsis(&self.synopsis, config.synopsis_format),
+            "tags": self.get_tag_paths(config),
+            "publisher": self.publisher,
+            "release_date": self
+                .release_date
+                .map(|date| date.format(&config.date_format).to_string()),
+            "language_code": self.language_code,
+            "language_tag": self.language_tag,
+            "isbn": self.isbn.as_ref().map(|isbn| &isbn.isbn_13),
+            "isbn_10": self.isbn.as_ref().and_then(|isbn| isbn.isbn_10.as_ref()),
+            "product_id": self.product_id,
+            "page_count": self.page_count,
+            "word_count": self.word_count,
+            "reading_time": self.reading_time,
+            "rating": self.rating,
+            "rating_count": self.rating_count,
+            "review_count": self.review_count,
+            "price": self.price,
+            "sale_price": self.sale_price,
+            "currency": self.currency,
+            "file_format": self.file_format,
+            "drm": self.drm,
+            "age_rating": self.age_rating,
+            "kind": self.kind.name(),
+            "narrators": self.narrators(),
+            "duration": self.duration,
+            "abridged": self.abridged,
         })
     }
 
+    pub fn get_column_value(&self, column: Column, img_path: &str, config: &Config) -> String {
+        fn or_empty<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(T::to_string).unwrap_or_default()
+        }
+
+        match column {
+            Column::Id => self.id.clone(),
+            Column::Title => self.title.clone(),
+            Column::Subtitle => or_empty(&self.subtitle),
+            Column::Authors => self.authors().join(&config.list_separator),
+            Column::Series => or_empty(&self.series_name),
+            Column::SeriesIndex => or_empty(&self.series_index),
+            Column::Cover => img_path.to_string(),
+            Column::Synopsis => format_synopsis(&self.synopsis, config.synopsis_format),
+            Column::Tags => join_tag_paths(&self.get_tag_paths(config), &config.tag_separator),
+            Column::Publisher => self.publisher.clone(),
+            Column::ReleaseDate => or_empty(
+                &self
+                    .release_date
+                    .map(|date| date.format(&config.date_format)),
+            ),
+            Column::LanguageCode => self.language_code.clone(),
+            Column::LanguageTag => self.language_tag.clone(),
+            Column::Isbn => or_empty(&self.isbn.as_ref().map(|isbn| &isbn.isbn_13)),
+            Column::Isbn10 => or_empty(&self.isbn.as_ref().and_then(|isbn| isbn.isbn_10.as_ref())),
+            Column::ProductId => or_empty(&self.product_id),
+            Column::PageCount => or_empty(&self.page_count),
+            Column::WordCount => or_empty(&self.word_count),
+            Column::ReadingTime => or_empty(&self.reading_time),
+            Column::Rating => or_empty(&self.rating),
+            Column::RatingCount => or_empty(&self.rating_count),
+            Column::ReviewCount => or_empty(&self.review_count),
+            Column::Price => or_empty(&self.price),
+            Column::SalePrice => or_empty(&self.sale_price),
+            Column::Currency => or_empty(&self.currency),
+            Column::FileFormat => or_empty(&self.file_format),
+            Column::Drm => or_empty(&self.drm),
+            Column::AgeRating => or_empty(&self.age_rating),
+            Column::Kind => self.kind.name().to_string(),
+            Column::Narrators => self.narrators().join(&config.list_separator),
+            Column::Duration => or_empty(&self.duration),
+            Column::Abridged => or_empty(&self.abridged),
+        }
+    }
+}
+
+pub trait Id {
+    fn from(self) -> Option<String>;
+    fn get_metadata(self, pb: &ProgressBar) -> Result<Metadata>;
+    fn get_book_page(self) -> Result<Html>;
+    fn get_series_book_ids(self) -> Result<Vec<String>>;
+    fn get_author_url(self) -> Result<String>;
+}
+
+impl Id for &str {
+    fn from(self) -> Option<String> {
+        // Any store locale, e.g. /tw/zh or /us/en, as the crawler accepts
+        let path = self
+            .trim()
+            .strip_prefix(KOBO_ORIGIN)?
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        let kind = [BookKind::Ebook, BookKind::Audiobook]
+            .into_iter()
+            .find(|kind| path.contains(kind.path_segment()))?;
+
+        let book_id = path
+            .rsplit_once('/')
+            .map(|(_, substring)| substring.trim().to_string())
+            .filter(|id| !id.is_empty())?;
+
+        Some(kind.to_book_id(&book_id))
+    }
+
+    fn get_metadata(self, pb: &ProgressBar) -> Result<Metadata> {
+        let book_page = self.get_book_page()?;
+        pb.inc(1);
+
+        Ok(Metadata::from_book_page(self, &book_page, pb))
+    }
+
     fn get_book_page(self) -> Result<Html> {
-        let book_page_url = format!("{}{}", BOOK_PATH, self);
-        let book_page_html = reqwest::blocking::get(book_page_url)?.text()?;
+        let (kind, id) = BookKind::split_book_id(self);
+        let book_page_url = format!("{}{}{}", STORE_PATH, kind.path_segment(), id);
+        let book_page_html = reqwest::blocking::get(book_page_url)?
+            .error_for_status()?
+            .text()?;
         let book_page = Html::parse_document(&book_page_html);
 
         Ok(book_page)
     }
+
+    fn get_series_book_ids(self) -> Result<Vec<String>> {
+        let Some(series_url) = self.get_book_page()?.get_series_url() else {
+            anyhow::bail!("{} is not part of a series", self);
+        };
+
+        let (kind, _) = BookKind::split_book_id(self);
+        crawl_book_ids(&series_url, kind)
+    }
+
+    fn get_author_url(self) -> Result<String> {
+        let Some(author_url) = self.get_book_page()?.get_author_url() else {
+            anyhow::bail!("{} has no contributor link", self);
+        };
+
+        Ok(author_url)
+    }
 }
 
 trait PageHtml {
     fn get_title(&self) -> String;
     fn get_subtitle(&self) -> Option<String>;
     fn get_authors_str(&self) -> String;
+    fn get_author_url(&self) -> Option<String>;
     fn get_series_name(&self) -> Option<String>;
     fn get_series_index(&self) -> Option<f64>;
+    fn get_series_url(&self) -> Option<String>;
     fn get_cover_url(&self) -> String;
     fn get_synopsis_html(&self) -> String;
-    fn get_tags_str(&self) -> String;
+    fn get_tag_paths(&self) -> Vec<Vec<String>>;
     fn get_publisher(&self) -> String;
-    fn get_release_date(&self) -> String;
-    fn get_language_code(&self) -> String;
-    fn get_isbn(&self) -> String;
+    fn get_release_date(&self) -> Option<NaiveDate>;
+    fn get_language(&self) -> Option<Language>;
+    fn get_identifier(&self) -> Option<Identifier>;
+    fn get_page_count(&self) -> Option<u32>;
+    fn get_word_count(&self) -> Option<u32>;
+    fn get_reading_time(&self) -> Option<f64>;
+    fn get_rating(&self) -> Option<f64>;
+    fn get_rating_count(&self) -> Option<u32>;
+    fn get_review_count(&self) -> Option<u32>;
+    fn get_price(&self) -> Option<f64>;
+    fn get_sale_price(&self) -> Option<f64>;
+    fn get_currency(&self) -> Option<String>;
+    fn get_file_format(&self) -> Option<String>;
+    fn get_drm(&self) -> Option<String>;
+    fn get_age_rating(&self) -> Option<String>;
+    fn get_narrators_str(&self) -> String;
+    fn get_duration(&self) -> Option<u32>;
+    fn get_abridged(&self) -> Option<bool>;
+    fn get_book_stat(&self, labels: &[&str]) -> Option<String>;
+    fn get_secondary_metadata(&self, labels: &[&str]) -> Option<String>;
+    fn get_item_prop(&self, name: &str) -> Option<String>;
+    fn get_json_ld(&self) -> Vec<Value>;
 }
 
 impl PageHtml for Html {
@@ -234,6 +630,18 @@ impl PageHtml for Html {
         authors_str
     }
 
+    fn get_author_url(&self) -> Option<String> {
+        let author_selector = Selector::parse("a.contributor-name").expect("Invalid selector");
+        let author_url = self
+            .select(&author_selector)
+            .next()?
+            .value()
+            .attr("href")
+            .map(to_absolute_url);
+
+        author_url
+    }
+
     fn get_series_name(&self) -> Option<String> {
         let series_name_selector =
             Selector::parse("a[data-track-info='{}']").expect("Invalid selector");
@@ -257,6 +665,19 @@ impl PageHtml for Html {
         series_index
     }
 
+    fn get_series_url(&self) -> Option<String> {
+        let series_url_selector =
+            Selector::parse("a[data-track-info='{}']").expect("Invalid selector");
+        let series_url = self
+            .select(&series_url_selector)
+            .next()?
+            .value()
+            .attr("href")
+            .map(to_absolute_url);
+
+        series_url
+    }
+
     fn get_cover_url(&self) -> String {
         let cover_selector = Selector::parse("link[as='image']").expect("Invalid selector");
         let cover_url = self
@@ -281,16 +702,36 @@ impl PageHtml for Html {
         synopsis_html
     }
 
-    fn get_tags_str(&self) -> String {
+    fn get_tag_paths(&self) -> Vec<Vec<String>> {
         let tag_selector =
             Selector::parse("a.rankingAnchor.description-anchor").expect("Invalid selector");
-        let mut tags_vec = self
-            .select(&tag_selector)
-            .map(|a| a.text().collect())
-            .collect::<Vec<String>>();
-        tags_vec.sort();
-        tags_vec.dedup();
-        tags_vec.join(",")
+        let mut breadcrumbs = Vec::<(Option<NodeId>, Vec<String>)>::new();
+        for a in self.select(&tag_selector) {
+            let breadcrumb_id = a
+                .ancestors()
+                .find(|node| {
+                    node.value()
+                        .as_element()
+                        .is_some_and(|element| element.name() == "li")
+                })
+                .map(|li| li.id());
+            let tag = a.text().collect::<String>().trim().to_string();
+            match breadcrumbs.last_mut() {
+                Some((id, tag_path)) if breadcrumb_id.is_some() && *id == breadcrumb_id => {
+                    tag_path.push(tag)
+                }
+                _ => breadcrumbs.push((breadcrumb_id, vec![tag])),
+            }
+        }
+
+        let mut tag_paths = Vec::<Vec<String>>::new();
+        for (_, tag_path) in breadcrumbs {
+            if !tag_paths.contains(&tag_path) {
+                tag_paths.push(tag_path);
+            }
+        }
+
+        tag_paths
     }
 
     fn get_publisher(&self) -> String {
@@ -309,61 +750,586 @@ impl PageHtml for Html {
         publisher
     }
 
-    fn get_release_date(&self) -> String {
+    fn get_release_date(&self) -> Option<NaiveDate> {
         let release_date_selector =
             Selector::parse("div.bookitem-secondary-metadata li > span").expect("Invalid selector");
         let release_date = self
             .select(&release_date_selector)
             .next()
-            .map(|span| {
-                let mut date = span
-                    .text()
-                    .collect::<String>()
-                    .replace(|char: char| !char.is_ascii_digit(), "-");
-                date.pop();
-
-                date
-            })
-            .unwrap_or_default();
+            .and_then(|span| parse_release_date(&span.text().collect::<String>()));
 
         release_date
     }
 
-    fn get_language_code(&self) -> String {
+    fn get_language(&self) -> Option<Language> {
         let language_selector =
             Selector::parse("div.bookitem-secondary-metadata li > span").expect("Invalid selector");
-        let language_code = self
+        let html_selector = Selector::parse("html[lang]").expect("Invalid selector");
+        let language = self
             .select(&language_selector)
             .nth(2)
-            .map(|span| span.text().collect::<String>())
-            .and_then(|language| match language.as_str() {
-                "中文" => Some("zh"),
-                "英文" => Some("en"),
-                "日文" => Some("ja"),
-                _ => None,
+            .and_then(|span| Language::from_name(&span.text().collect::<String>()))
+            .or_else(|| {
+                self.get_json_ld()
+                    .iter()
+                    .find_map(|json| match &json["inLanguage"] {
+                        Value::String(tag) => Language::from_tag(tag),
+                        Value::Object(language) => language
+                            .get("alternateName")
+                            .and_then(Value::as_str)
+                            .and_then(Language::from_tag)
+                            .or_else(|| {
+                                language
+                                    .get("name")
+                                    .and_then(Value::as_str)
+                                    .and_then(Language::from_name)
+                            }),
+                        _ => None,
+                    })
             })
-            .unwrap_or_default()
-            .to_string();
-
-        language_code
+            .or_else(|| {
+                self.select(&html_selector)
+                    .next()
+                    .and_then(|html| html.value().attr("lang"))
+                    .and_then(Language::from_tag)
+            });
+
+        language
     }
 
-    fn get_isbn(&self) -> String {
+    fn get_identifier(&self) -> Option<Identifier> {
         let isbn_selector =
             Selector::parse("div.bookitem-secondary-metadata li > span").expect("Invalid selector");
-        let isbn = self
+        let identifier = self
             .select(&isbn_selector)
             .nth(1)
-            .map(|span| span.text().collect())
+            .and_then(|span| Identifier::parse(&span.text().collect::<String>()));
+        if let Some(Identifier::Isbn(_) | Identifier::ProductId(_)) = identifier {
+            return identifier;
+        }
+
+        // An invalid value in the page is only kept if the JSON-LD has nothing better
+        let json_ld_identifier = self
+            .get_json_ld()
+            .iter()
+            .find_map(|json| json["isbn"].as_str().and_then(Identifier::parse));
+        match json_ld_identifier {
+            Some(Identifier::Invalid(_)) | None => identifier.or(json_ld_identifier),
+            json_ld_identifier => json_ld_identifier,
+        }
+    }
+
+    fn get_page_count(&self) -> Option<u32> {
+        let page_count = self
+            .get_book_stat(&["page", "頁", "ページ", "seite", "pagina", "página"])
+            .and_then(|pages| parse_number(&pages))
+            .or_else(|| {
+                self.get_json_ld()
+                    .iter()
+                    .find_map(|json| json_number(&json["numberOfPages"]))
+            })
+            .map(|pages| pages as u32);
+
+        page_count
+    }
+
+    fn get_word_count(&self) -> Option<u32> {
+        self.get_book_stat(&["word", "字", "wört", "mot", "palabra", "parol", "woord"])
+            .and_then(|words| parse_number(&words))
+            .map(|words| words as u32)
+    }
+
+    fn get_reading_time(&self) -> Option<f64> {
+        self.get_book_stat(&[
+            "hour", "小時", "時間", "stunde", "heure", "hora", "ora", "uur",
+        ])
+        .and_then(|hours| parse_number(&hours))
+    }
+
+    fn get_rating(&self) -> Option<f64> {
+        let rating = self
+            .get_item_prop("ratingValue")
+            .and_then(|rating| parse_number(&rating))
+            .or_else(|| {
+                self.get_json_ld()
+                    .iter()
+                    .find_map(|json| json_number(&json["aggregateRating"]["ratingValue"]))
+            });
+
+        rating
+    }
+
+    fn get_rating_count(&self) -> Option<u32> {
+        let rating_count = self
+            .get_item_prop("ratingCount")
+            .and_then(|count| parse_number(&count))
+            .or_else(|| {
+                self.get_json_ld()
+                    .iter()
+                    .find_map(|json| json_number(&json["aggregateRating"]["ratingCount"]))
+            })
+            .map(|count| count as u32);
+
+        rating_count
+    }
+
+    fn get_review_count(&self) -> Option<u32> {
+        let review_count = self
+            .get_item_prop("reviewCount")
+            .and_then(|count| parse_number(&count))
+            .or_else(|| {
+                self.get_json_ld()
+                    .iter()
+                    .find_map(|json| json_number(&json["aggregateRating"]["reviewCount"]))
+            })
+            .map(|count| count as u32);
+
+        review_count
+    }
+
+    fn get_price(&self) -> Option<f64> {
+        let original_price_selector =
+            Selector::parse("div.original-price span, span.original-price")
+                .expect("Invalid selector");
+        let active_price_selector =
+            Selector::parse("div.active-price span.price").expect("Invalid selector");
+        // Books that are not on sale show only the active price
+        let price = self
+            .select(&original_price_selector)
+            .find_map(|span| parse_price(&span.text().collect::<String>()))
+            .or_else(|| {
+                self.select(&active_price_selector)
+                    .find_map(|span| parse_price(&span.text().collect::<String>()))
+            })
+            .or_else(|| {
+                self.get_json_ld().iter().find_map(|json| {
+                    json_number(&json["offers"]["price"])
+                        .or_else(|| json_number(&json["offers"][0]["price"]))
+                })
+            });
+
+        price
+    }
+
+    fn get_sale_price(&self) -> Option<f64> {
+        let original_price_selector =
+            Selector::parse("div.original-price span, span.original-price")
+                .expect("Invalid selector");
+        let active_price_selector =
+            Selector::parse("div.active-price span.price").expect("Invalid selector");
+        let is_on_sale = self.select(&original_price_selector).next().is_some();
+        let sale_price = self
+            .select(&active_price_selector)
+            .find_map(|span| parse_price(&span.text().collect::<String>()))
+            .filter(|_| is_on_sale);
+
+        sale_price
+    }
+
+    fn get_currency(&self) -> Option<String> {
+        let currency = self.get_item_prop("priceCurrency").or_else(|| {
+            self.get_json_ld().iter().find_map(|json| {
+                json["offers"]["priceCurrency"]
+                    .as_str()
+                    .or_else(|| json["offers"][0]["priceCurrency"].as_str())
+                    .map(str::to_string)
+            })
+        });
+
+        currency
+    }
+
+    fn get_file_format(&self) -> Option<String> {
+        let file_format = self
+            .get_secondary_metadata(&["EPUB", "PDF"])
+            .map(|download_options| {
+                download_options
+                    .split_once('(')
+                    .map_or(download_options.as_str(), |(file_format, _)| file_format)
+                    .trim()
+                    .to_string()
+            })
+            .filter(|file_format| !file_format.is_empty());
+
+        file_format
+    }
+
+    fn get_drm(&self) -> Option<String> {
+        let drm = self
+            .get_secondary_metadata(&["EPUB", "PDF"])
+            .and_then(|download_options| {
+                download_options
+                    .split_once('(')
+                    .and_then(|(_, drm)| drm.split_once(')'))
+                    .map(|(drm, _)| drm.trim().to_string())
+            });
+
+        drm
+    }
+
+    fn get_age_rating(&self) -> Option<String> {
+        self.get_secondary_metadata(&["Age", "年齡", "年齢", "Alter", "Âge", "Edad", "Età"])
+    }
+
+    fn get_narrators_str(&self) -> String {
+        let narrators = self
+            .get_secondary_metadata(&[
+                "Narrat",
+                "朗讀",
+                "朗读",
+                "旁白",
+                "ナレーター",
+                "Sprecher",
+                "Lu par",
+                "Narrado",
+                "Letto da",
+            ])
+            .map(|narrators| {
+                narrators
+                    .split([',', '，', '、', '&'])
+                    .map(str::trim)
+                    .filter(|narrator| !narrator.is_empty())
+                    .collect::<Vec<&str>>()
+                    .join("&")
+            })
+            .or_else(|| {
+                self.get_json_ld().iter().find_map(|json| {
+                    let read_by = match &json["readBy"] {
+                        Value::Array(people) => people.clone(),
+                        Value::Null => return None,
+                        person => vec![person.clone()],
+                    };
+                    let narrators = read_by
+                        .iter()
+                        .filter_map(|person| person["name"].as_str().or(person.as_str()))
+                        .collect::<Vec<&str>>()
+                        .join("&");
+
+                    Some(narrators)
+                })
+            })
             .unwrap_or_default();
 
-        isbn
+        narrators
+    }
+
+    fn get_duration(&self) -> Option<u32> {
+        let duration = self
+            .get_secondary_metadata(&[
+                "Length",
+                "Duration",
+                "長度",
+                "時長",
+                "时长",
+                "再生時間",
+                "Dauer",
+                "Durée",
+                "Duración",
+                "Durata",
+            ])
+            .and_then(|duration| parse_duration(&duration))
+            .or_else(|| {
+                self.get_json_ld().iter().find_map(|json| {
+                    json["duration"]
+                        .as_str()
+                        .or_else(|| json["timeRequired"].as_str())
+                        .and_then(parse_duration)
+                })
+            });
+
+        duration
+    }
+
+    fn get_abridged(&self) -> Option<bool> {
+        let abridged = self
+            .get_secondary_metadata(&[
+                "bridged",
+                "節略",
+                "刪節",
+                "删节",
+                "完整版",
+                "gekürzt",
+                "Gekürzt",
+                "abrégé",
+                "Intégral",
+                "intégral",
+                "breviad",
+                "Íntegro",
+            ])
+            .and_then(|version| {
+                let version = version.to_lowercase();
+                let is_unabridged = [
+                    "unabridged",
+                    "完整版",
+                    "未刪節",
+                    "未删节",
+                    "ungekürzt",
+                    "intégral",
+                    "non abrégé",
+                    "íntegro",
+                    "integral",
+                ]
+                .iter()
+                .any(|keyword| version.contains(keyword));
+                let is_abridged = [
+                    "abridged", "節略", "刪節", "删节", "gekürzt", "abrégé", "abreviad",
+                ]
+                .iter()
+                .any(|keyword| version.contains(keyword));
+
+                match (is_unabridged, is_abridged) {
+                    (true, _) => Some(false),
+                    (false, true) => Some(true),
+                    _ => None,
+                }
+            })
+            .or_else(|| {
+                self.get_json_ld()
+                    .iter()
+                    .find_map(|json| json["abridged"].as_bool())
+            });
+
+        abridged
+    }
+
+    fn get_book_stat(&self, labels: &[&str]) -> Option<String> {
+        let book_stat_selector =
+            Selector::parse("div.book-stats div.column").expect("Invalid selector");
+        let value_selector = Selector::parse("strong").expect("Invalid selector");
+        let book_stat = self
+            .select(&book_stat_selector)
+            .find(|column| {
+                let label = column.text().collect::<String>().to_lowercase();
+                labels.iter().any(|keyword| label.contains(keyword))
+            })
+            .and_then(|column| column.select(&value_selector).next())
+            .map(|strong| strong.text().collect::<String>().trim().to_string());
+
+        book_stat
+    }
+
+    fn get_secondary_metadata(&self, labels: &[&str]) -> Option<String> {
+        let secondary_metadata_selector =
+            Selector::parse("div.bookitem-secondary-metadata li").expect("Invalid selector");
+        let secondary_metadata = self
+            .select(&secondary_metadata_selector)
+            .map(|li| li.text().collect::<String>())
+            .find(|text| labels.iter().any(|label| text.contains(label)))
+            .map(|text| {
+                text.split_once([':', '：'])
+                    .map_or(text.as_str(), |(_, value)| value)
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            });
+
+        secondary_metadata
+    }
+
+    fn get_item_prop(&self, name: &str) -> Option<String> {
+        let item_prop_selector =
+            Selector::parse(&format!("[itemprop='{}']", name)).expect("Invalid selector");
+        let item_prop = self.select(&item_prop_selector).next().map(|element| {
+            element
+                .value()
+                .attr("content")
+                .map(str::to_string)
+                .unwrap_or_else(|| element.text().collect::<String>().trim().to_string())
+        });
+
+        item_prop
+    }
+
+    fn get_json_ld(&self) -> Vec<Value> {
+        let json_ld_selector =
+            Selector::parse("script[type='application/ld+json']").expect("Invalid selector");
+        let json_ld = self
+            .select(&json_ld_selector)
+            .filter_map(|script| serde_json::from_str(&script.text().collect::<String>()).ok())
+            .flat_map(|json: Value| match json {
+                Value::Array(objects) => objects,
+                Value::Object(ref object) if object.contains_key("@graph") => {
+                    json["@graph"].as_array().cloned().unwrap_or_default()
+                }
+                _ => vec![json],
+            })
+            .collect();
+
+        json_ld
+    }
+}
+
+/// Parses numbers as shown on Kobo, e.g. `400`, `112k`, `10.8萬` or the mean of `11 - 13`
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.replace(',', "");
+    let numbers = text
+        .split(|char: char| !(char.is_ascii_digit() || char == '.'))
+        .filter_map(|number| number.parse::<f64>().ok())
+        .collect::<Vec<f64>>();
+    if numbers.is_empty() {
+        return None;
+    }
+
+    let multiplier = match text.to_lowercase() {
+        text if text.contains('k') || text.contains('千') => 1_000.0,
+        text if text.contains('萬') || text.contains('万') => 10_000.0,
+        text if text.contains('m') => 1_000_000.0,
+        _ => 1.0,
+    };
+    let mean = numbers.iter().sum::<f64>() / numbers.len() as f64;
+
+    Some(mean * multiplier)
+}
+
+/// Parses prices such as `NT$300`, `$12.99`, `$12.5`, `12,99 €` or `1.234,56 €`
+fn parse_price(text: &str) -> Option<f64> {
+    let amount = text
+        .chars()
+        .filter(|char| char.is_ascii_digit() || *char == '.' || *char == ',')
+        .collect::<String>();
+    let amount = amount.trim_matches([',', '.']);
+    // The last separator is the decimal point when both kinds appear. A lone kind is a
+    // decimal point only when it appears once and isn't followed by three digits, as in
+    // `¥1,320`.
+    let decimal_index = amount.rfind([',', '.']).filter(|&index| {
+        let separator = &amount[index..=index];
+        let other_separator = if separator == "," { '.' } else { ',' };
+        amount.contains(other_separator)
+            || (amount.matches(separator).count() == 1 && amount.len() - index - 1 != 3)
+    });
+    let amount = match decimal_index {
+        Some(index) => {
+            let (integer, fraction) = amount.split_at(index);
+            format!("{}.{}", integer.replace([',', '.'], ""), &fraction[1..])
+        }
+        None => amount.replace([',', '.'], ""),
+    };
+
+    amount.parse().ok()
+}
+
+/// Parses durations in minutes, e.g. `12 hours and 3 minutes`, `12 小時 3 分鐘`, `12:03:00`
+/// or ISO 8601's `PT12H3M`
+fn parse_duration(text: &str) -> Option<u32> {
+    let text = text.trim().to_lowercase();
+    let numbers = text
+        .split(|char: char| !(char.is_ascii_digit() || char == '.'))
+        .filter_map(|number| number.parse::<f64>().ok())
+        .collect::<Vec<f64>>();
+    if numbers.is_empty() {
+        return None;
+    }
+
+    let seconds = match text.contains(':') {
+        true => numbers
+            .iter()
+            .zip([3600.0, 60.0, 1.0])
+            .map(|(number, unit)| number * unit)
+            .sum::<f64>(),
+        false => {
+            let mut seconds = 0.0;
+            let mut rest = text.trim_start_matches("pt");
+            while let Some(start) = rest.find(|char: char| char.is_ascii_digit()) {
+                let rest_from_number = &rest[start..];
+                let end = rest_from_number
+                    .find(|char: char| !(char.is_ascii_digit() || char == '.'))
+                    .unwrap_or(rest_from_number.len());
+                let number = rest_from_number[..end].parse::<f64>().unwrap_or_default();
+                rest = &rest_from_number[end..];
+
+                let unit = rest.trim_start();
+                let unit = if [
+                    "h", "小時", "小时", "時間", "時", "std", "stunde", "ora", "uur",
+                ]
+                .iter()
+                .any(|hours| unit.starts_with(hours))
+                {
+                    3600.0
+                } else if ["s", "秒"].iter().any(|seconds| unit.starts_with(seconds)) {
+                    1.0
+                } else {
+                    60.0
+                };
+                seconds += number * unit;
+            }
+            seconds
+        }
+    };
+
+    Some((seconds / 60.0).round() as u32)
+}
+
+fn json_number(json: &Value) -> Option<f64> {
+    json.as_f64()
+        .or_else(|| json.as_str().and_then(parse_number))
+}
+
+#[cfg(test)]
+impl Metadata {
+    /// A scraped book for testing the output formats
+    pub fn sample() -> Self {
+        Self {
+            id: "mistborn-3".to_string(),
+            title: "Mistborn".to_string(),
+            subtitle: Some("The Final Empire".to_string()),
+            authors: "Brandon Sanderson".to_string(),
+            series_name: Some("Mistborn".to_string()),
+            series_index: Some(1.0),
+            cover: "https://cdn.kobo.com/book-images/mistborn-3.jpg".to_string(),
+            synopsis: "<p>For a thousand years the ash fell &amp; no flowers bloomed.</p>"
+                .to_string(),
+            tag_paths: vec![vec![
+                "Fiction & Literature".to_string(),
+                "Fantasy".to_string(),
+            ]],
+            publisher: "Tor Publishing Group".to_string(),
+            release_date: NaiveDate::from_ymd_opt(2010, 4, 1),
+            language_code: "en".to_string(),
+            language_tag: "en".to_string(),
+            isbn: Isbn::parse("9781429989817"),
+            product_id: None,
+            page_count: Some(672),
+            word_count: Some(215_000),
+            reading_time: Some(12.0),
+            rating: Some(4.6),
+            rating_count: Some(1_200),
+            review_count: Some(180),
+            price: Some(9.99),
+            sale_price: None,
+            currency: Some("USD".to_string()),
+            file_format: Some("EPUB 3".to_string()),
+            drm: Some("Adobe DRM".to_string()),
+            age_rating: None,
+            kind: BookKind::Ebook,
+            narrators: String::new(),
+            duration: None,
+            abridged: None,
+        }
+    }
+
+    pub fn with_synopsis(self, synopsis: &str) -> Self {
+        Self {
+            synopsis: synopsis.to_string(),
+            ..self
+        }
+    }
+
+    pub fn with_publisher(self, publisher: &str) -> Self {
+        Self {
+            publisher: publisher.to_string(),
+            ..self
+        }
+    }
+
+    pub fn with_kind(self, kind: BookKind) -> Self {
+        Self { kind, ..self }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::output::{CsvWriter, OutputBook, OutputWriter, CSV_FILE_PATH};
     use std::fs::{self, remove_dir, remove_file};
 
     #[test]
@@ -387,52 +1353,66 @@ mod tests {
         assert_eq!(book_id, Some("tSfRgYbwtzGWxEne-NJKWw".to_string()))
     }
 
+    #[test]
+    fn input_other_locale_url() {
+        let book_id = Id::from("https://www.kobo.com/us/en/ebook/mistborn-3?sId=1#reviews");
+
+        assert_eq!(book_id, Some("mistborn-3".to_string()))
+    }
+
+    #[test]
+    fn input_kobo_audiobook_url() {
+        let book_id = Id::from("https://www.kobo.com/tw/zh/audiobook/mistborn-1");
+
+        assert_eq!(book_id, Some("audiobook/mistborn-1".to_string()))
+    }
+
     #[test]
     fn test_book_title() -> Result<()> {
         let book_title = "tSfRgYbwtzGWxEne-NJKWw".get_book_page()?.get_title();
 
-        Ok(assert_eq!(book_title, "迷霧之子首部曲：最後帝國"))
+        assert_eq!(book_title, "迷霧之子首部曲：最後帝國");
+        Ok(())
     }
 
     #[test]
     fn test_book_subtitle() -> Result<()> {
         let book_subtitle = "2kbdRVwUITa5gQeowqSvKQ".get_book_page()?.get_subtitle();
 
-        Ok(assert_eq!(book_subtitle, None))
+        assert_eq!(book_subtitle, None);
+        Ok(())
     }
 
     #[test]
     fn test_book_authors() -> Result<()> {
         let book_authors = "let-it-snow-5".get_book_page()?.get_authors_str();
 
-        Ok(assert_eq!(
-            book_authors,
-            "John Green&Lauren Myracle&Maureen Johnson"
-        ))
+        assert_eq!(book_authors, "John Green&Lauren Myracle&Maureen Johnson");
+        Ok(())
     }
 
     #[test]
     fn test_book_series_name() -> Result<()> {
         let book_series_name = "defiant-68".get_book_page()?.get_series_name();
 
-        Ok(assert_eq!(
-            book_series_name,
-            Some("The Skyward Series".to_string())
-        ))
+        assert_eq!(book_series_name, Some("The Skyward Series".to_string()));
+        Ok(())
     }
 
     #[test]
     fn test_book_series_index() -> Result<()> {
         let book_series_index = "YOylwW_Z6jKJP7HpcEr0Ig".get_book_page()?.get_series_index();
 
-        Ok(assert_eq!(book_series_index, Some(13.5)))
+        assert_eq!(book_series_index, Some(13.5));
+        Ok(())
     }
 
     #[test]
     fn test_book_cover() -> Result<()> {
         let book_cover = "tSfRgYbwtzGWxEne-NJKWw".get_book_page()?.get_cover_url();
 
-        Ok(assert_eq!(book_cover, "https://cdn.kobo.com/book-images/28289ceb-265c-488a-bf08-ae3424588a91/1650/2200/100/False/tSfRgYbwtzGWxEne-NJKWw.jpg"))
+        assert_eq!(book_cover, "https://cdn.kobo.com/book-images/28289ceb-265c-488a-bf08-ae3424588a91/1650/2200/100/False/tSfRgYbwtzGWxEne-NJKWw.jpg");
+        Ok(())
     }
 
     #[test]
@@ -444,47 +1424,206 @@ mod tests {
 
         let test_book_synopsis = "<p>美國亞馬遜讀者評鑑最高票，全球暢銷三千萬冊著作已逝奇幻大師，羅伯特．喬丹指定接班人</p><p>09年「時光之輪」接班作《風起雲湧》，打敗丹布朗新書《失落的符號》，空降紐約時報排行榜冠軍作者</p><p>《出版人週刊》、《軌跡雜誌》、《美國圖書館協會誌》、《克科斯評論》極優評價</p><p>美國最大邦諾連鎖書店頭號選書作者、西班牙UPC科幻大獎得主</p><p>2005年出道即獲《浪漫時代 Romantic Times》奇幻史詩大獎</p><p>2006、2007年入選美國科奇幻地位最高約翰．坎伯新人獎</p><p>超級天才新星作家──布蘭登．山德森全新華麗鉅作</p><p>架構壯閣媲美「冰與火之歌」，精采絕妙更勝「夜巡者」</p><p>「這本書有完美縝密的架構……我極度推薦給任何渴求一本好書的讀者。」──羅蘋．荷布（「刺客」系列作者）</p><p>「 我很驕傲、很榮幸、很迫切想要介紹這位作者和他的作品給所有讀者。」──灰鷹／譚光磊（版權經紀人）</p><p>「 一個繁複的革命計畫，透過作者縝密的佈局，逐步實行。小說結構完整，前後緊密聯繫；布蘭登．山德森能否抽空來寫部推理小說？」──紗卡（推理文學研究會MLR）</p><p>一個不可能成功的絕望計畫，而勝利，將是最糟的代價……</p><p>迷霧之子</p><p>首部曲：最後帝國</p><p>Mistborn: The Final Empire</p><p>「他說：任何人都會背叛你，任何人。」</p><p>如果背叛無所不在，如果一切非你以為那樣，</p><p>你有勇氣知道真相嗎？</p><p>這是個英雄殞落，邪惡籠罩的世界，再不見光明與顏色。</p><p>入夜後，迷霧四起，誰也不曉得，藏身在白茫霧色之後的，會是什麼……</p><p>千年前，善惡雙方決戰，良善一方的英雄歷經千辛萬苦，終於抵達傳說中的聖地「昇華之井」，準備和黑暗勢力一決生死。</p><p>可是，命運女神沒有站在良善這方。</p><p>最後，邪惡擊潰英雄，一統天下，並自稱「統御主」，同時建立「最後帝國」，號稱千秋萬代、永不崩塌。至此，世界隨之變遷，從此綠色不再，所有植物都轉為褐黃，天空永遠陰霾，不間斷地下著灰燼，彷彿是浩劫過後的殘破荒地。入夜之後，濃霧四起，籠罩大地。</p><p>統御主如神一般無敵，以絕對的權力和極端的高壓恐怖統治著最後帝國。他更以凶殘的手段鎮壓平民百姓，不分國籍種族通通打為奴隸階級，通稱「司卡」。司卡人活在無止盡的悲慘和恐懼之中，千年來的奴役讓他們早已沒有希望，沒有任何過去的記憶。</p><p>如今，一線生機浮現。二名貴族與司卡混血卻天賦異稟、身負使命的街頭小人物，即將編織一場前所未有的騙局，進行一項絕不可能成功的計畫，只為了獲得最糟糕的代價──勝利……</p><p>迷霧之子三部曲　Mistborn Trilogy──</p><p>首部曲：最後帝國The Final Empire</p><p>二部曲：昇華之井The Well of Ascension 2010年4月出版</p><p>終部曲：永世英雄The Hero of Ages 2010年6月出版</p>";
 
-        Ok(assert_eq!(book_synopsis, test_book_synopsis))
+        assert_eq!(book_synopsis, test_book_synopsis);
+        Ok(())
     }
 
     #[test]
     fn test_book_tags() -> Result<()> {
-        let book_tags = "i-357".get_book_page()?.get_tags_str();
+        let mut book_tags_vec = "i-357".get_book_page()?.get_tag_paths().concat();
+        book_tags_vec.sort();
+        book_tags_vec.dedup();
+        let book_tags = book_tags_vec.join(",");
 
         let mut test_tags_vec = "青少年 - YA,漫畫、圖畫小說和漫畫,兒童,漫畫、圖像小說與連環漫畫,科幻小說與奇幻小說,幻想".split(',').collect::<Vec<&str>>();
         test_tags_vec.sort();
         test_tags_vec.dedup();
         let test_tags = test_tags_vec.join(",");
 
-        Ok(assert_eq!(book_tags, test_tags))
+        assert_eq!(book_tags, test_tags);
+        Ok(())
+    }
+
+    #[test]
+    fn test_book_tag_paths() {
+        let book_page = Html::parse_document(
+            r#"<div class="category-rankings"><ul>
+                <li><a class="rankingAnchor description-anchor">漫畫</a> &gt; <a class="rankingAnchor description-anchor">圖像小說</a></li>
+                <li><a class="rankingAnchor description-anchor">幻想</a></li>
+                <li><a class="rankingAnchor description-anchor">漫畫</a> &gt; <a class="rankingAnchor description-anchor">圖像小說</a></li>
+            </ul></div>"#,
+        );
+        let book_tag_paths = book_page.get_tag_paths();
+
+        assert_eq!(
+            book_tag_paths,
+            vec![
+                vec!["漫畫".to_string(), "圖像小說".to_string()],
+                vec!["幻想".to_string()]
+            ]
+        )
     }
 
     #[test]
     fn test_book_publisher() -> Result<()> {
         let book_publisher = "silent-witch-1".get_book_page()?.get_publisher();
 
-        Ok(assert_eq!(book_publisher, "台灣角川"))
+        assert_eq!(book_publisher, "台灣角川");
+        Ok(())
     }
 
     #[test]
     fn test_book_release_date() -> Result<()> {
         let book_release_date = "silent-witch-1".get_book_page()?.get_release_date();
 
-        Ok(assert_eq!(book_release_date, "2022-5-27"))
+        assert_eq!(book_release_date, NaiveDate::from_ymd_opt(2022, 5, 27));
+        Ok(())
+    }
+
+    #[test]
+    fn test_book_language() -> Result<()> {
+        let book_language = "mistborn-trilogy".get_book_page()?.get_language();
+
+        assert_eq!(
+            book_language,
+            Some(Language {
+                code: "en".to_string(),
+                tag: "en".to_string()
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_book_language_fallback() {
+        let book_page = Html::parse_document(
+            r#"<html lang="zh-TW"><head><script type="application/ld+json">{"@type":"Book","inLanguage":"ko"}</script></head></html>"#,
+        );
+        let book_language = book_page.get_language();
+
+        assert_eq!(
+            book_language,
+            Some(Language {
+                code: "ko".to_string(),
+                tag: "ko".to_string()
+            })
+        )
     }
 
     #[test]
-    fn test_book_language_code() -> Result<()> {
-        let book_language_code = "mistborn-trilogy".get_book_page()?.get_language_code();
+    fn invalid_isbn_falls_back_to_json_ld() {
+        let book_page = Html::parse_document(
+            r#"<script type="application/ld+json">{"@type":"Book","isbn":"9781429989817"}</script>
+            <div class="bookitem-secondary-metadata"><ul>
+                <li>出版日期：<span>2010年4月1日</span></li>
+                <li>ISBN：<span>2022年5月27日</span></li>
+            </ul></div>"#,
+        );
 
-        Ok(assert_eq!(book_language_code, "en"))
+        assert_eq!(
+            book_page.get_identifier(),
+            Some(Identifier::Isbn(Isbn {
+                isbn_13: "9781429989817".to_string(),
+                isbn_10: Some("1429989815".to_string())
+            }))
+        )
     }
 
     #[test]
     fn test_book_isbn() -> Result<()> {
-        let book_isbn = "mistborn-trilogy".get_book_page()?.get_isbn();
+        let book_identifier = "mistborn-trilogy".get_book_page()?.get_identifier();
+
+        assert_eq!(
+            book_identifier,
+            Some(Identifier::Isbn(Isbn {
+                isbn_13: "9781429989817".to_string(),
+                isbn_10: Some("1429989815".to_string())
+            }))
+        );
+        Ok(())
+    }
 
-        Ok(assert_eq!(book_isbn, "9781429989817"))
+    #[test]
+    fn test_book_stats() {
+        let book_page = Html::parse_document(
+            r#"<div class="book-stats">
+                <div class="column"><strong>448</strong><span>頁數</span></div>
+                <div class="column"><strong>11 - 13</strong><span>小時閱讀</span></div>
+                <div class="column"><strong>112k</strong><span>總字數</span></div>
+            </div>
+            <div class="bookitem-secondary-metadata"><ul>
+                <li>下載選項：<span>EPUB 3 (Adobe DRM)</span></li>
+            </ul></div>
+            <div class="original-price"><span>NT$1,200</span></div>
+            <div class="active-price"><span class="price">NT$840</span></div>
+            <meta itemprop="ratingValue" content="4.6">
+            <script type="application/ld+json">
+                {"@type":"Book","aggregateRating":{"ratingCount":"31"},"offers":{"priceCurrency":"TWD"}}
+            </script>"#,
+        );
+
+        assert_eq!(book_page.get_page_count(), Some(448));
+        assert_eq!(book_page.get_reading_time(), Some(12.0));
+        assert_eq!(book_page.get_word_count(), Some(112_000));
+        assert_eq!(book_page.get_file_format(), Some("EPUB 3".to_string()));
+        assert_eq!(book_page.get_drm(), Some("Adobe DRM".to_string()));
+        assert_eq!(book_page.get_price(), Some(1200.0));
+        assert_eq!(book_page.get_sale_price(), Some(840.0));
+        assert_eq!(book_page.get_rating(), Some(4.6));
+        assert_eq!(book_page.get_rating_count(), Some(31));
+        assert_eq!(book_page.get_currency(), Some("TWD".to_string()));
+        assert_eq!(book_page.get_age_rating(), None)
+    }
+
+    #[test]
+    fn test_audiobook_details() {
+        let book_page = Html::parse_document(
+            r#"<div class="bookitem-secondary-metadata"><ul>
+                <li>朗讀者：Michael Kramer、Kate Reading</li>
+                <li>長度：24 小時 39 分鐘</li>
+                <li>版本：完整版</li>
+            </ul></div>
+            <script type="application/ld+json">{"@type":"Audiobook","duration":"PT24H39M","abridged":true}</script>"#,
+        );
+
+        assert_eq!(book_page.get_narrators_str(), "Michael Kramer&Kate Reading");
+        assert_eq!(book_page.get_duration(), Some(1479));
+        assert_eq!(book_page.get_abridged(), Some(false))
+    }
+
+    #[test]
+    fn parse_durations() {
+        let durations =
+            ["12 hours and 3 minutes", "PT1H30M", "10:25:30", "45 min"].map(parse_duration);
+
+        assert_eq!(durations, [Some(723), Some(90), Some(626), Some(45)])
+    }
+
+    #[test]
+    fn parse_prices() {
+        let prices = [
+            "NT$300",
+            "$12.99",
+            "$12.5",
+            "12,99 €",
+            "¥1,320",
+            "1.234,56 €",
+            "$1,234,567",
+        ]
+        .map(parse_price);
+
+        assert_eq!(
+            prices,
+            [
+                Some(300.0),
+                Some(12.99),
+                Some(12.5),
+                Some(12.99),
+                Some(1320.0),
+                Some(1234.56),
+                Some(1234567.0)
+            ]
+        )
     }
 
     #[test]
@@ -500,19 +1639,137 @@ mod tests {
             series_index: None,
             cover: "https://cdn.kobo.com/book-images/04b3ec92-aaa7-4757-b1ac-ff143aed0848/1650/2200/100/False/J2FjG5BoyDiEQfQn-uI4OA.jpg".to_string(),
             synopsis: "<p><strong>人生就是會有很多不便利、不舒服，</strong><br>\n<strong>這間有點慘澹的便利店，卻為我們撐起了閃閃發光的空間……</strong></p>\n<p><strong>艱難時刻的光亮之書</strong><br>\n<strong>一間便利店，接通了我們的幸福人生</strong></p>\n<p><strong>★韓國年度最受歡迎小說</strong><br>\n<strong>★銷售破70萬冊，25個都市特選年度之書</strong><br>\n<strong>★Yes24年度之書，韓國各大書店排行榜總冠軍，口碑直追《歡迎光臨夢境百貨》</strong><br>\n<strong>★電子書平台「米莉的書齋」年度圖書第二名</strong><br>\n<strong>★韓國中央圖書館館員推薦之書</strong><br>\n<strong>★售出泰、日、簡中、台灣、越南、印尼等多國版權</strong><br>\n<strong>★影視改編熱烈進行中</strong></p>\n<p>◎全球獨家收錄：作者手寫給台灣讀者的問候箋</p>\n<p>謝哲青＼作家、旅行家<br>\n盧建彰＼導演<br>\n李盈姿＼芒草心慈善協會祕書長<br>\n別家門市＼「超商系」插畫粉絲團<br>\n太咪＼作家、《太咪瘋韓國》版主<br>\n山女孩kit＼作家<br>\n方億玲＼而立書店店長<br>\n徐慧玲＼聆韵企管顧問創辦人──鼓掌推薦</p>\n<p>◎韓國讀者口碑推薦：</p>\n<p>‧這是一本我想推薦給所有人的人生之書。你讀的時候，很可能一會兒哭一會兒笑，但不知不覺間心頭就暖呼呼了。<br>\n‧擦肩而過的人，竟然可以成為彼此生活前進的支撐。一本讓我看到人生力量的書。<br>\n‧我的眼角掛著淚，嘴邊帶著笑。多虧這本書，讓我熬過疫病籠罩的日子。<br>\n‧哭著，笑著，心也跟著暖了。<br>\n‧場景不陌生、人物不陌生，就連裡面的衝突也不陌生，但是人們彼此表達善意卻是這個冷陌時代最需要的態度。</p>\n<p><strong>這間有點不便利，卻讓人想一再前往的便利店，</strong><br>\n<strong>藏著能在艱難生活中給你安慰的各樣物品。</strong></p>\n<p><strong>買一送一的喜悅、三角飯糰模樣的悲傷，</strong><br>\n<strong>以及一萬元所帶來的四次歡笑，</strong><br>\n<strong>充滿特別的故事與奇妙商品組合的便利店，時時歡迎您！</strong></p>\n<p>廉女士搭火車途中，驚覺錢包不見了，此時一通電話來告知，說在車站撿到了包包，還嚅囁詢問能否借用點錢買便當吃。廉女士答應了。</p>\n<p>果然如她所想，對方是一名流浪漢。廉女士在拿回包包時，告知對方，歡迎他來自己經營的便利店吃便當。</p>\n<p>這間便利店生意不太好，店員更是各種邊緣人的組合：上了年紀還為子女操碎了心的婦人；準備公務員考試多年的年輕女孩；五十多歲靠微薄薪水養家的一家之主。而廉女士為了如同家人般的員工，努力把店鋪撐了下來。</p>\n<p>然而，大夜班店員突然辭職，讓她苦惱不已。就在這時，常來吃報廢便當的流浪漢竟陰錯陽差接下這份工作……</p>\n<p>\u{f0d8}</p>\n<p><strong>只差一點點就陷落於孤立和衝突的人生，</strong><br>\n<strong>如何在這個小小的空間裡悄悄獲得喘息？</strong><br>\n<strong>一間不夠便利的便利店，又如何接通大家的幸福人生？</strong></p>\n<p><strong>◎便利店「幫人生加值」小語</strong></p>\n<p>※我問，支持妳的力量究竟是什麼？<br>\n她說，人生本來就是不斷解決問題，既然都要解決問題，那就努力選還可以的問題來解。</p>\n<p>※便利店是個人們來來去去的空間，無論店員還是客人，都只是短暫停留的過客。便利店就像間加油站，讓人們用物品或金錢為自己加值。</p>\n<p>※為什麼開心？因為炸雞？因為爸爸的陪伴？其實無論是什麼都沒關係，因為能一起吃雞的就是家人。</p>\n<p>※人生就是關係，關係的根本就是溝通。我發現只要我們能跟身旁的人交心，幸福其實離我們不遠。</p>\n<p>※巴布狄倫的外婆曾經告訴他，幸福不是在通往目標路途上的某樣東西，而是那條路本身就是幸福。你所遇見的每個人，都在苦苦掙扎著與什麼對抗，所以你必須親切待人。</p>\n<p>【作者簡介】<strong>金浩然（김호연）</strong></p>\n<p><strong>全天候說故事的人</strong><br>\n<strong>人生目標：透過電影、漫畫、小說講述各樣故事</strong></p>\n<p>1974年出生於首爾。畢業於高麗大學人文學院國語國文學科。初入職場時，在電影公司參與創作的劇本《諜變任務》被改編為電影，自此成為編劇。<br>\n第二份工作是擔任漫畫策劃人員，撰寫的《人體實驗區》獲得第一屆富川漫畫故事競賽大獎，自此成了漫畫腳本家。在出版社擔任小說編輯一陣子之後，決定轉換跑道，成為為全職作家。<br>\n他努力實踐「年輕時就該任意揮灑文字」的理念，以長篇小說《望遠洞兄弟》奪下2013年第9屆世界文學獎的優秀獎，展開小說家生涯之路。此後還推出長篇小說《情敵》《幽靈作家》《浮士德》及散文集《每天寫，重新寫，寫到最後》，並參與電影《烈日追殺》的劇本及《南漢山城》的策劃。<br>\n2021年繼《望遠洞兄弟》以後，再度推出描繪鄰里人情的溫暖故事《不便利的便利店》，成為口碑長紅的年度暢銷冠軍，並售出多國版權，影視改編也熱烈進行中。</p>\n<p>＊獲獎紀錄：</p>\n<p>《人體實驗區》獲第一屆富川漫畫故事競賽<br>\n《望遠洞兄弟》獲2013年第9屆世界文學獎優秀獎<br>\n《不便利的便利店》獲韓國超過25個都市選爲年度之書</p>\n<p>譯者 <strong>陳品芳</strong><br>\n政大韓文系畢，曾於台韓兩地職場打滾，目前為韓中專職譯者。熱愛各種二、三次元娛樂，享受在趕稿與耍廢之間穿梭的自由時光。譯有《剝削首爾》《讓尼采當你的心理師》《K-Pop征服世界的秘密》等書。</p>\n".to_string(),
-            tags: "小說與文學".to_string(),
+            tag_paths: vec![vec!["小說與文學".to_string()]],
             publisher: "寂寞".to_string(),
-            release_date: "2022-9-1".to_string(),
+            release_date: NaiveDate::from_ymd_opt(2022, 9, 1),
             language_code: "zh".to_string(),
-            isbn: "9786269593859".to_string()
+            language_tag: "zh".to_string(),
+            isbn: Isbn::parse("9786269593859"),
+            product_id: None,
+            page_count: None,
+            word_count: None,
+            reading_time: None,
+            rating: None,
+            rating_count: None,
+            review_count: None,
+            price: None,
+            sale_price: None,
+            currency: Some("TWD".to_string()),
+            file_format: None,
+            drm: None,
+            age_rating: None,
+            kind: BookKind::Ebook,
+            narrators: String::new(),
+            duration: None,
+            abridged: None,
+        };
+
+        // Stats, ratings and prices change over time, so only their presence is checked here
+        // and their scraping is checked against a fixture in test_book_page_metadata
+        assert!(book_metadata.page_count.is_some_and(|pages| pages > 0));
+        assert!(book_metadata.word_count.is_some_and(|words| words > 0));
+        assert!(book_metadata
+            .rating
+            .is_none_or(|rating| (0.0..=5.0).contains(&rating)));
+        assert!(book_metadata.price.is_some_and(|price| price > 0.0));
+        assert!(book_metadata.file_format.is_some());
+        let book_metadata = Metadata {
+            page_count: None,
+            word_count: None,
+            reading_time: None,
+            rating: None,
+            rating_count: None,
+            review_count: None,
+            price: None,
+            sale_price: None,
+            file_format: None,
+            drm: None,
+            ..book_metadata
         };
 
-        Ok(assert_eq!(book_metadata, test_book_metadata))
+        assert_eq!(book_metadata, test_book_metadata);
+        Ok(())
+    }
+
+    #[test]
+    fn test_book_page_metadata() {
+        let book_page = Html::parse_document(
+            r#"<html lang="zh-TW"><head>
+                <link as="image" href="https://cdn.kobo.com/book-images/04b3ec92/353/569/90/False/J2FjG5BoyDiEQfQn-uI4OA.jpg">
+                <script type="application/ld+json">
+                    {"@type":"Book","aggregateRating":{"ratingValue":"4.2","ratingCount":"1,204","reviewCount":"37"},"offers":{"price":"300","priceCurrency":"TWD"}}
+                </script>
+            </head><body>
+                <div class="item-info">
+                    <h1> 不便利的便利店 </h1>
+                    <span class="subtitle">불편한 편의점</span>
+                    <a class="contributor-name" href="/tw/zh/author/kim">金浩然</a>
+                    <span class="sequenced-name-prefix">Book 2 - </span><a data-track-info='{}' href="/tw/zh/series/1">Soul</a>
+                </div>
+                <div class="synopsis-description"><p>synopsis</p></div>
+                <div class="category-rankings"><ul>
+                    <li><a class="rankingAnchor description-anchor">小說與文學</a></li>
+                </ul></div>
+                <div class="book-stats">
+                    <div class="column"><strong>304</strong><span>頁數</span></div>
+                    <div class="column"><strong>6 - 7</strong><span>小時閱讀</span></div>
+                    <div class="column"><strong>10.8萬</strong><span>總字數</span></div>
+                </div>
+                <div class="active-price"><span class="price">NT$252</span></div>
+                <div class="bookitem-secondary-metadata"><ul>
+                    <li>寂寞</li>
+                    <li>出版日期：<span>2022年9月1日</span></li>
+                    <li>ISBN：<span>9786269593859</span></li>
+                    <li>語言：<span>中文</span></li>
+                    <li>下載選項：<span>EPUB 3 (Adobe DRM)</span></li>
+                    <li>年齡分級：<span>12+</span></li>
+                </ul></div>
+            </body></html>"#,
+        );
+        let book_metadata =
+            Metadata::from_book_page("J2FjG5BoyDiEQfQn-uI4OA", &book_page, &ProgressBar::hidden());
+
+        assert_eq!(
+            book_metadata,
+            Metadata {
+                id: "J2FjG5BoyDiEQfQn-uI4OA".to_string(),
+                title: "不便利的便利店".to_string(),
+                subtitle: Some("불편한 편의점".to_string()),
+                authors: "金浩然".to_string(),
+                series_name: Some("Soul".to_string()),
+                series_index: Some(2.0),
+                cover: "https://cdn.kobo.com/book-images/04b3ec92/1650/2200/100/False/J2FjG5BoyDiEQfQn-uI4OA.jpg".to_string(),
+                synopsis: "<p>synopsis</p>".to_string(),
+                tag_paths: vec![vec!["小說與文學".to_string()]],
+                publisher: "寂寞".to_string(),
+                release_date: NaiveDate::from_ymd_opt(2022, 9, 1),
+                language_code: "zh".to_string(),
+                language_tag: "zh".to_string(),
+                isbn: Isbn::parse("9786269593859"),
+                product_id: None,
+                page_count: Some(304),
+                word_count: Some(108_000),
+                reading_time: Some(6.5),
+                rating: Some(4.2),
+                rating_count: Some(1204),
+                review_count: Some(37),
+                price: Some(252.0),
+                sale_price: None,
+                currency: Some("TWD".to_string()),
+                file_format: Some("EPUB 3".to_string()),
+                drm: Some("Adobe DRM".to_string()),
+                age_rating: Some("12+".to_string()),
+                kind: BookKind::Ebook,
+                narrators: String::new(),
+                duration: None,
+                abridged: None,
+            }
+        )
     }
 
     #[test]
     fn test_append_to_csv_file() -> Result<()> {
-        Metadata {
+        let metadata = Metadata {
             id: "id".to_string(),
             title: "title".to_string(),
             subtitle: Some("subtitle".to_string()),
@@ -521,12 +1778,38 @@ mod tests {
             series_index: Some(0.0),
             cover: "https://cdn.kobo.com/book-images/04b3ec92-aaa7-4757-b1ac-ff143aed0848/1650/2200/100/False/J2FjG5BoyDiEQfQn-uI4OA.jpg".to_string(),
             synopsis: "<p>synopsis</p>".to_string(),
-            tags: "t,a,g,s".to_string(),
+            tag_paths: vec![
+                vec!["t".to_string(), "a".to_string()],
+                vec!["g,s".to_string()],
+            ],
             publisher: "publisher".to_string(),
-            release_date: "0000-0-0".to_string(),
+            release_date: NaiveDate::from_ymd_opt(0, 1, 1),
             language_code: "lang".to_string(),
-            isbn: "0000000000000".to_string()
-        }.append_to_csv_file(&ProgressBar::hidden())?;
+            language_tag: "lang-tag".to_string(),
+            isbn: Isbn::parse("9781429989817"),
+            product_id: Some("product-id".to_string()),
+            page_count: Some(0),
+            word_count: Some(0),
+            reading_time: Some(0.5),
+            rating: Some(5.0),
+            rating_count: Some(0),
+            review_count: None,
+            price: Some(9.99),
+            sale_price: None,
+            currency: Some("USD".to_string()),
+            file_format: Some("EPUB".to_string()),
+            drm: Some("DRM-Free".to_string()),
+            age_rating: None,
+            kind: BookKind::Audiobook,
+            narrators: "narr&ators".to_string(),
+            duration: Some(90),
+            abridged: Some(false),
+        };
+        let config = Config::default();
+        let mut csv_writer = CsvWriter::default();
+        csv_writer.begin(&config)?;
+        csv_writer.write(&OutputBook::new(&metadata), &config, &ProgressBar::hidden())?;
+        csv_writer.finish(&config)?;
 
         let csv_file = fs::read_to_string(CSV_FILE_PATH)?.trim().to_string();
         let mut test_csv_wtr = csv::Writer::from_writer(vec![]);
@@ -541,17 +1824,36 @@ mod tests {
             "Synopsis (HTML)",
             "Tag(s)",
             "Publisher",
-            "Release Date (yyyy-m-d)",
+            "Release Date (yyyy-mm-dd)",
             "Language Code (ISO 639-1)",
+            "Language Tag (BCP 47)",
             "ISBN",
+            "ISBN-10",
+            "Kobo Product ID",
+            "Pages",
+            "Words",
+            "Reading Time (hours)",
+            "Rating",
+            "Rating Count",
+            "Review Count",
+            "Price",
+            "Sale Price",
+            "Currency",
+            "File Format",
+            "DRM",
+            "Age Rating",
+            "Kind",
+            "Narrator(s)",
+            "Duration (minutes)",
+            "Abridged",
         ])?;
-        let test_csv = "ID,Title,Subtitle,Author(s),Series,Series Index,Cover Path,Synopsis (HTML),Tag(s),Publisher,Release Date (yyyy-m-d),Language Code (ISO 639-1),ISBN\n\
-        id,title,subtitle,auth&ors,series name,0,./img/1.jpg,<p>synopsis</p>,\"t,a,g,s\",publisher,0000-0-0,lang,0000000000000";
+        let test_csv = "ID,Title,Subtitle,Author(s),Series,Series Index,Cover Path,Synopsis (HTML),Tag(s),Publisher,Release Date (yyyy-mm-dd),Language Code (ISO 639-1),Language Tag (BCP 47),ISBN,ISBN-10,Kobo Product ID,Pages,Words,Reading Time (hours),Rating,Rating Count,Review Count,Price,Sale Price,Currency,File Format,DRM,Age Rating,Kind,Narrator(s),Duration (minutes),Abridged\n\
+        id,title,subtitle,auth&ors,series name,0,./img/1.jpg,<p>synopsis</p>,\"t.a,g;s\",publisher,0000-01-01,lang,lang-tag,9781429989817,1429989815,product-id,0,0,0.5,5,0,,9.99,,USD,EPUB,DRM-Free,,audiobook,narr&ators,90,false";
         assert_eq!(csv_file, test_csv);
 
         let img_path = csv_file
             .split('\n')
-            .last()
+            .next_back()
             .and_then(|last_line| last_line.split(',').nth(6))
             .unwrap_or_default();
         assert!(Path::new(img_path).exists());
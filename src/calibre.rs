@@ -0,0 +1,727 @@
+use crate::{
+    book::Metadata,
+    config::Config,
+    language::to_iso_639_3_code,
+    name::family_name_first,
+    output::{OutputBook, OutputWriter},
+    tag::to_calibre_tags,
+    xml::escape_xml,
+};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, Utc};
+use indicatif::ProgressBar;
+use rusqlite::{functions::FunctionFlags, params, Connection, OptionalExtension};
+use std::{
+    fs::{copy, create_dir_all, read_dir, remove_dir, rename, write},
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+const DATABASE_FILE: &str = "metadata.db";
+const COVER_FILE: &str = "cover.jpg";
+const OPF_FILE: &str = "metadata.opf";
+const OPF_DIR: &str = "./opf";
+/// Calibre's date for unknown publication dates
+const UNDEFINED_DATE: &str = "0101-01-01 00:00:00+00:00";
+const MAX_PATH_COMPONENT_LENGTH: usize = 40;
+
+/// A book as stored in a Calibre library
+#[derive(Debug, PartialEq, Default)]
+struct CalibreBook {
+    title: String,
+    authors: Vec<String>,
+    series: Option<String>,
+    series_index: Option<f64>,
+    tags: Vec<String>,
+    publisher: Option<String>,
+    pubdate: Option<NaiveDate>,
+    language: Option<String>,
+    isbn: Option<String>,
+    kobo_id: String,
+    comments: Option<String>,
+}
+
+impl CalibreBook {
+    fn from_metadata(metadata: &Metadata, config: &Config) -> Self {
+        let non_empty = |text: String| Some(text).filter(|text| !text.is_empty());
+
+        Self {
+            title: metadata.title().to_string(),
+            authors: metadata.authors().into_iter().map(str::to_string).collect(),
+            series: metadata.series_name().map(str::to_string),
+            series_index: metadata.series_index(),
+            tags: metadata
+                .get_tag_paths(config)
+                .into_iter()
+                .map(|tag_path| to_calibre_tags(&[tag_path]))
+                .collect(),
+            publisher: non_empty(metadata.publisher().to_string()),
+            pubdate: metadata.release_date(),
+            language: non_empty(metadata.language_code().to_string()),
+            isbn: metadata.isbn().map(|isbn| isbn.isbn_13.clone()),
+            kobo_id: metadata.book_id(),
+            comments: non_empty(metadata.synopsis(config.synopsis_format)),
+        }
+    }
+
+    fn author_sort(&self) -> String {
+        self.authors
+            .iter()
+            .map(|author| family_name_first(author))
+            .collect::<Vec<String>>()
+            .join(" & ")
+    }
+
+    /// Folder of the book relative to the library, e.g. `Brandon Sanderson/Mistborn (12)`
+    fn path(&self, book_id: i64) -> String {
+        let author = self.authors.first().map_or("Unknown", String::as_str);
+
+        format!(
+            "{}/{} ({})",
+            to_path_component(author),
+            to_path_component(&self.title),
+            book_id
+        )
+    }
+
+    /// OPF package of the book, with its ID in a Calibre library if it is in one
+    fn to_opf(&self, book_id: Option<i64>, uuid: &str, cover_href: &str) -> String {
+        let mut metadata = Vec::<String>::new();
+        if let Some(book_id) = book_id {
+            metadata.push(format!(
+                r#"<dc:identifier opf:scheme="calibre" id="calibre_id">{}</dc:identifier>"#,
+                book_id
+            ));
+        }
+        metadata.push(format!(
+            r#"<dc:identifier opf:scheme="uuid" id="uuid_id">{}</dc:identifier>"#,
+            escape_xml(uuid)
+        ));
+        metadata.push(format!("<dc:title>{}</dc:title>", escape_xml(&self.title)));
+        for author in &self.authors {
+            metadata.push(format!(
+                r#"<dc:creator opf:file-as="{}" opf:role="aut">{}</dc:creator>"#,
+                escape_xml(&family_name_first(author)),
+                escape_xml(author)
+            ));
+        }
+        if let Some(publisher) = &self.publisher {
+            metadata.push(format!(
+                "<dc:publisher>{}</dc:publisher>",
+                escape_xml(publisher)
+            ));
+        }
+        if let Some(pubdate) = self.pubdate {
+            metadata.push(format!(
+                "<dc:date>{}</dc:date>",
+                pubdate.format("%Y-%m-%dT00:00:00+00:00")
+            ));
+        }
+        if let Some(comments) = &self.comments {
+            metadata.push(format!(
+                "<dc:description>{}</dc:description>",
+                escape_xml(comments)
+            ));
+        }
+        if let Some(language) = &self.language {
+            metadata.push(format!(
+                "<dc:language>{}</dc:language>",
+                escape_xml(language)
+            ));
+        }
+        if let Some(isbn) = &self.isbn {
+            metadata.push(format!(
+                r#"<dc:identifier opf:scheme="ISBN">{}</dc:identifier>"#,
+                isbn
+            ));
+        }
+        metadata.push(format!(
+            r#"<dc:identifier opf:scheme="KOBO">{}</dc:identifier>"#,
+            escape_xml(&self.kobo_id)
+        ));
+        for tag in &self.tags {
+            metadata.push(format!("<dc:subject>{}</dc:subject>", escape_xml(tag)));
+        }
+        if let Some(series) = &self.series {
+            metadata.push(format!(
+                r#"<meta name="calibre:series" content="{}"/>"#,
+                escape_xml(series)
+            ));
+            metadata.push(format!(
+                r#"<meta name="calibre:series_index" content="{}"/>"#,
+                self.series_index.unwrap_or(1.0)
+            ));
+        }
+        metadata.push(format!(
+            r#"<meta name="calibre:title_sort" content="{}"/>"#,
+            escape_xml(&title_sort(&self.title))
+        ));
+
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" unique-identifier="uuid_id" version="2.0">
+    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+        {}
+    </metadata>
+    <guide>
+        <reference type="cover" title="Cover" href="{}"/>
+    </guide>
+</package>
+"#,
+            metadata.join("\n        "),
+            escape_xml(cover_href)
+        )
+    }
+}
+
+/// Adds books to a Calibre library, or updates the book with the same ISBN or Kobo ID,
+/// then writes its cover and metadata.opf into its folder
+#[derive(Default)]
+pub struct CalibreWriter {
+    library: Option<(PathBuf, Connection)>,
+}
+
+impl OutputWriter for CalibreWriter {
+    fn begin(&mut self, config: &Config) -> Result<()> {
+        let library = config
+            .calibre_library
+            .clone()
+            .ok_or_else(|| anyhow!("--format calibre needs --calibre-library"))?;
+        let db = open_library(&library)?;
+        self.library = Some((library, db));
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some((library, db)) = self.library.as_mut() else {
+            anyhow::bail!("The Calibre writer was not begun");
+        };
+        let calibre_book = CalibreBook::from_metadata(book.metadata, config);
+        let cover_path = book.cover_path()?;
+        pb.inc(1);
+
+        let transaction = db.transaction()?;
+        let library_book = upsert_book(&transaction, &calibre_book)?;
+        let book_dir = library.join(&library_book.path);
+        if let Some(previous_dir) = library_book
+            .previous_path
+            .map(|previous_path| library.join(previous_path))
+            .filter(|previous_dir| previous_dir.is_dir())
+        {
+            move_book_dir(&previous_dir, &book_dir)?;
+        }
+        create_dir_all(&book_dir)?;
+        copy(cover_path, book_dir.join(COVER_FILE))?;
+        write(
+            book_dir.join(OPF_FILE),
+            calibre_book.to_opf(Some(library_book.id), &library_book.uuid, COVER_FILE),
+        )?;
+        transaction.commit()?;
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        self.library = None;
+
+        Ok(())
+    }
+}
+
+/// Writes each book to ./opf/<Kobo ID>.opf, an OPF file as Calibre writes next to a book,
+/// with the cover in ./img
+pub struct OpfWriter;
+
+impl OutputWriter for OpfWriter {
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        create_dir_all(OPF_DIR)?;
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let opf_book = CalibreBook::from_metadata(book.metadata, config);
+        // ./img/1.jpg as seen from ./opf
+        let cover_href = format!("../{}", book.cover_path()?.trim_start_matches("./"));
+        pb.inc(1);
+
+        write(
+            Path::new(OPF_DIR).join(format!("{}.opf", to_path_component(&opf_book.kobo_id))),
+            opf_book.to_opf(None, &Uuid::new_v4().to_string(), &cover_href),
+        )?;
+        pb.inc(1);
+
+        Ok(())
+    }
+}
+
+/// Moves a book folder after a title or author change, removing the author folder it
+/// leaves empty, as Calibre does
+fn move_book_dir(previous_dir: &Path, book_dir: &Path) -> Result<()> {
+    if let Some(author_dir) = book_dir.parent() {
+        create_dir_all(author_dir)?;
+    }
+    rename(previous_dir, book_dir)?;
+    if let Some(previous_author_dir) = previous_dir.parent() {
+        if read_dir(previous_author_dir)?.next().is_none() {
+            remove_dir(previous_author_dir)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens the metadata.db of a library with the SQL functions its triggers call
+fn open_library(library: &Path) -> Result<Connection> {
+    let database_path = library.join(DATABASE_FILE);
+    if !database_path.is_file() {
+        anyhow::bail!("{} is not a Calibre library", library.display());
+    }
+
+    let db = Connection::open(database_path)?;
+    register_functions(&db)?;
+
+    Ok(db)
+}
+
+fn register_functions(db: &Connection) -> Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8;
+    db.create_scalar_function(
+        "title_sort",
+        1,
+        flags | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(title_sort(&ctx.get::<String>(0)?)),
+    )?;
+    db.create_scalar_function("uuid4", 0, flags, |_| Ok(Uuid::new_v4().to_string()))?;
+
+    Ok(())
+}
+
+/// A book as inserted or updated in the library
+#[derive(Debug, PartialEq)]
+struct LibraryBook {
+    id: i64,
+    /// Folder of the book relative to the library
+    path: String,
+    uuid: String,
+    /// Folder the book had before its title or first author changed
+    previous_path: Option<String>,
+}
+
+/// Inserts or updates a book with its links
+fn upsert_book(db: &Connection, book: &CalibreBook) -> Result<LibraryBook> {
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f+00:00").to_string();
+    let pubdate = book.pubdate.map_or(UNDEFINED_DATE.to_string(), |date| {
+        date.format("%Y-%m-%d 00:00:00+00:00").to_string()
+    });
+
+    let mut previous_path = None;
+    let book_id = match find_book(db, book)? {
+        Some(book_id) => {
+            let path: String = db.query_row(
+                "SELECT path FROM books WHERE id = ?1",
+                params![book_id],
+                |row| row.get(0),
+            )?;
+            if path != book.path(book_id) {
+                previous_path = Some(path);
+            }
+            db.execute(
+                "UPDATE books SET title = ?1, series_index = ?2, author_sort = ?3, pubdate = ?4,
+                    isbn = ?5, last_modified = ?6, path = ?7
+                WHERE id = ?8",
+                params![
+                    book.title,
+                    book.series_index.unwrap_or(1.0),
+                    book.author_sort(),
+                    pubdate,
+                    book.isbn.clone().unwrap_or_default(),
+                    now,
+                    book.path(book_id),
+                    book_id
+                ],
+            )?;
+            book_id
+        }
+        None => {
+            db.execute(
+                "INSERT INTO books (title, series_index, author_sort, timestamp, pubdate, isbn,
+                    last_modified, path)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?4, '')",
+                params![
+                    book.title,
+                    book.series_index.unwrap_or(1.0),
+                    book.author_sort(),
+                    now,
+                    pubdate,
+                    book.isbn.clone().unwrap_or_default(),
+                ],
+            )?;
+            let book_id = db.last_insert_rowid();
+            db.execute(
+                "UPDATE books SET path = ?1 WHERE id = ?2",
+                params![book.path(book_id), book_id],
+            )?;
+            book_id
+        }
+    };
+    db.execute(
+        "UPDATE books SET has_cover = 1 WHERE id = ?1",
+        params![book_id],
+    )?;
+
+    db.execute(
+        "DELETE FROM books_authors_link WHERE book = ?1",
+        params![book_id],
+    )?;
+    for author in &book.authors {
+        db.execute(
+            "INSERT OR IGNORE INTO authors (name, sort) VALUES (?1, ?2)",
+            params![author, family_name_first(author)],
+        )?;
+        db.execute(
+            "INSERT OR IGNORE INTO books_authors_link (book, author)
+            SELECT ?1, id FROM authors WHERE name = ?2",
+            params![book_id, author],
+        )?;
+    }
+
+    db.execute(
+        "DELETE FROM books_series_link WHERE book = ?1",
+        params![book_id],
+    )?;
+    if let Some(series) = &book.series {
+        db.execute(
+            "INSERT OR IGNORE INTO series (name, sort) VALUES (?1, ?2)",
+            params![series, title_sort(series)],
+        )?;
+        db.execute(
+            "INSERT INTO books_series_link (book, series) SELECT ?1, id FROM series WHERE name = ?2",
+            params![book_id, series],
+        )?;
+    }
+
+    db.execute(
+        "DELETE FROM books_tags_link WHERE book = ?1",
+        params![book_id],
+    )?;
+    for tag in &book.tags {
+        db.execute(
+            "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+            params![tag],
+        )?;
+        db.execute(
+            "INSERT OR IGNORE INTO books_tags_link (book, tag) SELECT ?1, id FROM tags WHERE name = ?2",
+            params![book_id, tag],
+        )?;
+    }
+
+    db.execute(
+        "DELETE FROM books_publishers_link WHERE book = ?1",
+        params![book_id],
+    )?;
+    if let Some(publisher) = &book.publisher {
+        db.execute(
+            "INSERT OR IGNORE INTO publishers (name) VALUES (?1)",
+            params![publisher],
+        )?;
+        db.execute(
+            "INSERT INTO books_publishers_link (book, publisher)
+            SELECT ?1, id FROM publishers WHERE name = ?2",
+            params![book_id, publisher],
+        )?;
+    }
+
+    db.execute(
+        "DELETE FROM books_languages_link WHERE book = ?1",
+        params![book_id],
+    )?;
+    if let Some(language) = book.language.as_deref().and_then(to_iso_639_3_code) {
+        db.execute(
+            "INSERT OR IGNORE INTO languages (lang_code) VALUES (?1)",
+            params![language],
+        )?;
+        db.execute(
+            "INSERT INTO books_languages_link (book, lang_code, item_order)
+            SELECT ?1, id, 0 FROM languages WHERE lang_code = ?2",
+            params![book_id, language],
+        )?;
+    }
+
+    let identifiers = [("isbn", book.isbn.as_ref()), ("kobo", Some(&book.kobo_id))];
+    for (identifier_type, value) in identifiers {
+        if let Some(value) = value {
+            db.execute(
+                "INSERT OR REPLACE INTO identifiers (book, type, val) VALUES (?1, ?2, ?3)",
+                params![book_id, identifier_type, value],
+            )?;
+        }
+    }
+
+    match &book.comments {
+        Some(comments) => db.execute(
+            "INSERT OR REPLACE INTO comments (book, text) VALUES (?1, ?2)",
+            params![book_id, comments],
+        )?,
+        None => db.execute("DELETE FROM comments WHERE book = ?1", params![book_id])?,
+    };
+
+    let (path, uuid) = db.query_row(
+        "SELECT path, uuid FROM books WHERE id = ?1",
+        params![book_id],
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            ))
+        },
+    )?;
+
+    Ok(LibraryBook {
+        id: book_id,
+        path,
+        uuid,
+        previous_path,
+    })
+}
+
+/// Finds a book of the library with the same ISBN or Kobo ID
+fn find_book(db: &Connection, book: &CalibreBook) -> Result<Option<i64>> {
+    let book_id = db
+        .query_row(
+            "SELECT book FROM identifiers
+            WHERE (type = 'isbn' AND val = ?1) OR (type = 'kobo' AND val = ?2)
+            ORDER BY book LIMIT 1",
+            params![book.isbn, book.kobo_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(book_id)
+}
+
+/// Moves leading English articles to the end, e.g. `Final Empire, The`
+fn title_sort(title: &str) -> String {
+    for article in ["The ", "A ", "An "] {
+        if let Some(rest) = title.strip_prefix(article) {
+            return format!("{}, {}", rest, article.trim());
+        }
+    }
+
+    title.to_string()
+}
+
+fn to_path_component(name: &str) -> String {
+    let component = name
+        .chars()
+        .map(|char| match char {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            char if char.is_control() => '_',
+            char => char,
+        })
+        .take(MAX_PATH_COMPONENT_LENGTH)
+        .collect::<String>();
+
+    component.trim().trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calibre_library() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        register_functions(&db)?;
+        db.execute_batch(
+            r#"CREATE TABLE books (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL DEFAULT 'Unknown',
+                sort TEXT COLLATE NOCASE, timestamp TIMESTAMP, pubdate TIMESTAMP,
+                series_index REAL NOT NULL DEFAULT 1.0, author_sort TEXT COLLATE NOCASE,
+                isbn TEXT DEFAULT "" COLLATE NOCASE, path TEXT NOT NULL DEFAULT "", uuid TEXT,
+                has_cover BOOL DEFAULT 0, last_modified TIMESTAMP
+            );
+            CREATE TRIGGER books_insert_trg AFTER INSERT ON books BEGIN
+                UPDATE books SET sort = title_sort(NEW.title), uuid = uuid4() WHERE id = NEW.id;
+            END;
+            CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, sort TEXT COLLATE NOCASE, UNIQUE(name));
+            CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, author INTEGER NOT NULL, UNIQUE(book, author));
+            CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, sort TEXT COLLATE NOCASE, UNIQUE(name));
+            CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, series INTEGER NOT NULL, UNIQUE(book));
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, UNIQUE(name));
+            CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, tag INTEGER NOT NULL, UNIQUE(book, tag));
+            CREATE TABLE publishers (id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, sort TEXT COLLATE NOCASE, UNIQUE(name));
+            CREATE TABLE books_publishers_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, publisher INTEGER NOT NULL, UNIQUE(book));
+            CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, type TEXT NOT NULL DEFAULT "isbn" COLLATE NOCASE, val TEXT NOT NULL COLLATE NOCASE, UNIQUE(book, type));
+            CREATE TABLE comments (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, text TEXT NOT NULL COLLATE NOCASE, UNIQUE(book));
+            CREATE TABLE languages (id INTEGER PRIMARY KEY, lang_code TEXT NOT NULL COLLATE NOCASE, link TEXT NOT NULL DEFAULT '', UNIQUE(lang_code));
+            CREATE TABLE books_languages_link (id INTEGER PRIMARY KEY, book INTEGER NOT NULL, lang_code INTEGER NOT NULL, item_order INTEGER NOT NULL DEFAULT 0, UNIQUE(book, lang_code));"#,
+        )?;
+
+        Ok(db)
+    }
+
+    fn mistborn() -> CalibreBook {
+        CalibreBook {
+            title: "The Final Empire".to_string(),
+            authors: vec!["Brandon Sanderson".to_string()],
+            series: Some("Mistborn".to_string()),
+            series_index: Some(1.0),
+            tags: vec!["Fiction & Literature.Fantasy".to_string()],
+            publisher: Some("Tor".to_string()),
+            pubdate: NaiveDate::from_ymd_opt(2010, 4, 1),
+            language: Some("en".to_string()),
+            isbn: Some("9781429989817".to_string()),
+            kobo_id: "mistborn-3".to_string(),
+            comments: Some("<p>synopsis</p>".to_string()),
+        }
+    }
+
+    #[test]
+    fn upsert_calibre_book() -> Result<()> {
+        let db = calibre_library()?;
+        let library_book = upsert_book(&db, &mistborn())?;
+        let updated_book = CalibreBook {
+            isbn: None,
+            series_index: Some(1.5),
+            ..mistborn()
+        };
+        let updated_library_book = upsert_book(&db, &updated_book)?;
+
+        assert_eq!(library_book.path, "Brandon Sanderson/The Final Empire (1)");
+        assert_eq!(updated_library_book, library_book);
+        let book = db.query_row(
+            "SELECT sort, author_sort, series_index, (SELECT COUNT(*) FROM books) FROM books",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        assert_eq!(
+            book,
+            (
+                "Final Empire, The".to_string(),
+                "Sanderson, Brandon".to_string(),
+                1.5,
+                1
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn write_calibre_opf() -> Result<()> {
+        let opf = mistborn().to_opf(Some(1), "uuid", COVER_FILE);
+        let opf = roxmltree::Document::parse(&opf)?;
+        let creator = opf
+            .descendants()
+            .find(|node| node.has_tag_name("creator"))
+            .map(|node| {
+                (
+                    node.text(),
+                    node.attribute(("http://www.idpf.org/2007/opf", "file-as")),
+                )
+            });
+
+        assert_eq!(
+            creator,
+            Some((Some("Brandon Sanderson"), Some("Sanderson, Brandon")))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn write_standalone_opf() -> Result<()> {
+        let opf = mistborn().to_opf(None, "uuid", "../img/1.jpg");
+        let opf = roxmltree::Document::parse(&opf)?;
+        let schemes = opf
+            .descendants()
+            .filter(|node| node.has_tag_name("identifier"))
+            .filter_map(|node| node.attribute(("http://www.idpf.org/2007/opf", "scheme")))
+            .collect::<Vec<&str>>();
+        let cover_href = opf
+            .descendants()
+            .find(|node| node.has_tag_name("reference"))
+            .and_then(|node| node.attribute("href"));
+
+        assert_eq!(schemes, vec!["uuid", "ISBN", "KOBO"]);
+        assert_eq!(cover_href, Some("../img/1.jpg"));
+        Ok(())
+    }
+
+    #[test]
+    fn retitle_calibre_book() -> Result<()> {
+        let db = calibre_library()?;
+        upsert_book(&db, &mistborn())?;
+        let retitled_book = CalibreBook {
+            title: "Mistborn".to_string(),
+            ..mistborn()
+        };
+        let library_book = upsert_book(&db, &retitled_book)?;
+
+        assert_eq!(
+            (
+                library_book.path.as_str(),
+                library_book.previous_path.as_deref()
+            ),
+            (
+                "Brandon Sanderson/Mistborn (1)",
+                Some("Brandon Sanderson/The Final Empire (1)")
+            )
+        );
+        let language: String = db.query_row(
+            "SELECT languages.lang_code FROM books_languages_link
+            JOIN languages ON languages.id = books_languages_link.lang_code",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(language, "eng");
+        Ok(())
+    }
+
+    #[test]
+    fn link_calibre_book_languages() -> Result<()> {
+        let db = calibre_library()?;
+        for (kobo_id, language) in [("mistborn-sv", "sv"), ("mistborn-el", "el")] {
+            upsert_book(
+                &db,
+                &CalibreBook {
+                    language: Some(language.to_string()),
+                    kobo_id: kobo_id.to_string(),
+                    isbn: None,
+                    ..mistborn()
+                },
+            )?;
+        }
+
+        let mut statement = db.prepare(
+            "SELECT languages.lang_code FROM books_languages_link
+            JOIN languages ON languages.id = books_languages_link.lang_code
+            ORDER BY books_languages_link.book",
+        )?;
+        let languages = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        assert_eq!(languages, vec!["swe", "ell"]);
+        Ok(())
+    }
+
+    #[test]
+    fn move_retitled_book_dir() -> Result<()> {
+        let library = std::env::temp_dir().join("kobo-books-metadata-downloader-library");
+        let _ = std::fs::remove_dir_all(&library);
+        let previous_dir = library.join("Brandon Sanderson/The Final Empire (1)");
+        create_dir_all(&previous_dir)?;
+        write(previous_dir.join(COVER_FILE), [0xFF, 0xD8])?;
+        let book_dir = library.join("Sanderson/Mistborn (1)");
+
+        move_book_dir(&previous_dir, &book_dir)?;
+
+        let moved = (
+            book_dir.join(COVER_FILE).is_file(),
+            library.join("Brandon Sanderson").exists(),
+        );
+        std::fs::remove_dir_all(&library)?;
+        assert_eq!(moved, (true, false));
+        Ok(())
+    }
+}
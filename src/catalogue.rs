@@ -0,0 +1,498 @@
+use crate::{
+    book::Metadata,
+    config::Config,
+    output::{OutputBook, OutputWriter},
+    tag::to_calibre_tags,
+};
+use anyhow::Result;
+use chrono::Utc;
+use clap::Args;
+use indicatif::ProgressBar;
+use rusqlite::{params, Connection};
+use std::{fs::read, path::Path};
+
+pub const CATALOGUE_PATH: &str = "./metadata.sqlite";
+/// Schema changes, applied in order; `PRAGMA user_version` records how many have run
+const MIGRATIONS: [&str; 2] = [
+    // Books are keyed on their book ID, so audiobooks as audiobook/<Kobo ID>, apart from
+    // ebooks with the same Kobo ID
+    "CREATE TABLE series (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE
+    );
+    CREATE TABLE books (
+        id TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        title TEXT NOT NULL,
+        subtitle TEXT,
+        series_id INTEGER REFERENCES series (id),
+        series_index REAL,
+        synopsis TEXT,
+        publisher TEXT,
+        release_date TEXT,
+        language_code TEXT,
+        language_tag TEXT,
+        isbn TEXT,
+        isbn_10 TEXT,
+        product_id TEXT,
+        page_count INTEGER,
+        word_count INTEGER,
+        reading_time REAL,
+        duration INTEGER,
+        abridged INTEGER,
+        rating REAL,
+        rating_count INTEGER,
+        review_count INTEGER,
+        price REAL,
+        sale_price REAL,
+        currency TEXT,
+        file_format TEXT,
+        drm TEXT,
+        age_rating TEXT,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE contributors (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE book_contributors (
+        book_id TEXT NOT NULL REFERENCES books (id) ON DELETE CASCADE,
+        contributor_id INTEGER NOT NULL REFERENCES contributors (id),
+        role TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (book_id, contributor_id, role)
+    );
+    CREATE TABLE tags (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE book_tags (
+        book_id TEXT NOT NULL REFERENCES books (id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags (id),
+        PRIMARY KEY (book_id, tag_id)
+    );
+    CREATE TABLE covers (
+        book_id TEXT PRIMARY KEY REFERENCES books (id) ON DELETE CASCADE,
+        url TEXT NOT NULL,
+        image BLOB NOT NULL
+    );",
+    "CREATE INDEX books_series ON books (series_id, series_index);
+    CREATE INDEX books_isbn ON books (isbn);
+    CREATE INDEX book_contributors_contributor ON book_contributors (contributor_id);
+    CREATE INDEX book_tags_tag ON book_tags (tag_id);",
+];
+
+/// Opens the catalogue, creating it or migrating it to the latest schema
+pub fn open_catalogue(path: &Path) -> Result<Connection> {
+    let mut db = Connection::open(path)?;
+    db.pragma_update(None, "foreign_keys", true)?;
+    migrate(&mut db)?;
+
+    Ok(db)
+}
+
+fn migrate(db: &mut Connection) -> Result<()> {
+    let version: usize = db.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        anyhow::bail!(
+            "The catalogue has schema version {}, newer than this version supports ({})",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = db.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Adds the books to the ./metadata.sqlite catalogue, or updates those whose Kobo ID is
+/// already there, committing each book on its own
+#[derive(Default)]
+pub struct CatalogueWriter {
+    db: Option<Connection>,
+}
+
+impl OutputWriter for CatalogueWriter {
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        self.db = Some(open_catalogue(Path::new(CATALOGUE_PATH))?);
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some(db) = self.db.as_mut() else {
+            anyhow::bail!("The catalogue writer was not begun");
+        };
+        let cover = read(book.cover_path()?)?;
+        pb.inc(1);
+
+        let transaction = db.transaction()?;
+        upsert_book(&transaction, book.metadata, &cover, config)?;
+        transaction.commit()?;
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        self.db = None;
+
+        Ok(())
+    }
+}
+
+/// Inserts or updates a book with its contributors, tags and cover
+fn upsert_book(db: &Connection, metadata: &Metadata, cover: &[u8], config: &Config) -> Result<()> {
+    let book_id = &metadata.book_id();
+    let series_id = match metadata.series_name() {
+        Some(series_name) => Some(upsert_series(db, series_name)?),
+        None => None,
+    };
+    let isbn = metadata.isbn();
+    db.execute(
+        "INSERT INTO books (
+            id, kind, title, subtitle, series_id, series_index, synopsis, publisher,
+            release_date, language_code, language_tag, isbn, isbn_10, product_id,
+            page_count, word_count, reading_time, duration, abridged, rating, rating_count,
+            review_count, price, sale_price, currency, file_format, drm, age_rating,
+            updated_at
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18,
+            ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29
+        )
+        ON CONFLICT (id) DO UPDATE SET
+            kind = excluded.kind, title = excluded.title, subtitle = excluded.subtitle,
+            series_id = excluded.series_id, series_index = excluded.series_index,
+            synopsis = excluded.synopsis, publisher = excluded.publisher,
+            release_date = excluded.release_date, language_code = excluded.language_code,
+            language_tag = excluded.language_tag, isbn = excluded.isbn,
+            isbn_10 = excluded.isbn_10, product_id = excluded.product_id,
+            page_count = excluded.page_count, word_count = excluded.word_count,
+            reading_time = excluded.reading_time, duration = excluded.duration,
+            abridged = excluded.abridged, rating = excluded.rating,
+            rating_count = excluded.rating_count, review_count = excluded.review_count,
+            price = excluded.price, sale_price = excluded.sale_price,
+            currency = excluded.currency, file_format = excluded.file_format,
+            drm = excluded.drm, age_rating = excluded.age_rating,
+            updated_at = excluded.updated_at",
+        params![
+            book_id,
+            metadata.kind().name(),
+            metadata.title(),
+            metadata.subtitle(),
+            series_id,
+            metadata.series_index(),
+            metadata.synopsis(config.synopsis_format),
+            metadata.publisher(),
+            metadata.release_date().map(|date| date.to_string()),
+            metadata.language_code(),
+            metadata.language_tag(),
+            isbn.map(|isbn| &isbn.isbn_13),
+            isbn.and_then(|isbn| isbn.isbn_10.as_ref()),
+            metadata.product_id(),
+            metadata.page_count(),
+            metadata.word_count(),
+            metadata.reading_time(),
+            metadata.duration(),
+            metadata.abridged(),
+            metadata.rating(),
+            metadata.rating_count(),
+            metadata.review_count(),
+            metadata.price(),
+            metadata.sale_price(),
+            metadata.currency(),
+            metadata.file_format(),
+            metadata.drm(),
+            metadata.age_rating(),
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    set_contributors(db, book_id, "author", &metadata.authors())?;
+    set_contributors(db, book_id, "narrator", &metadata.narrators())?;
+    set_tags(
+        db,
+        book_id,
+        &metadata
+            .get_tag_paths(config)
+            .into_iter()
+            .map(|tag_path| to_calibre_tags(&[tag_path]))
+            .collect::<Vec<String>>(),
+    )?;
+    set_cover(db, book_id, metadata.cover_url(), cover)?;
+
+    Ok(())
+}
+
+/// Returns the ID of the series, adding it if it is new
+pub fn upsert_series(db: &Connection, name: &str) -> Result<i64> {
+    db.execute(
+        "INSERT OR IGNORE INTO series (name) VALUES (?1)",
+        params![name],
+    )?;
+    let series_id = db.query_row(
+        "SELECT id FROM series WHERE name = ?1",
+        params![name],
+        |row| row.get(0),
+    )?;
+
+    Ok(series_id)
+}
+
+/// Replaces the contributors of a book in a role, e.g. `author` or `narrator`
+pub fn set_contributors(db: &Connection, book_id: &str, role: &str, names: &[&str]) -> Result<()> {
+    db.execute(
+        "DELETE FROM book_contributors WHERE book_id = ?1 AND role = ?2",
+        params![book_id, role],
+    )?;
+    for (position, name) in names.iter().enumerate() {
+        db.execute(
+            "INSERT OR IGNORE INTO contributors (name) VALUES (?1)",
+            params![name],
+        )?;
+        db.execute(
+            "INSERT OR IGNORE INTO book_contributors (book_id, contributor_id, role, position)
+            SELECT ?1, id, ?2, ?3 FROM contributors WHERE name = ?4",
+            params![book_id, role, position, name],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn set_tags(db: &Connection, book_id: &str, tags: &[String]) -> Result<()> {
+    db.execute("DELETE FROM book_tags WHERE book_id = ?1", params![book_id])?;
+    for tag in tags {
+        db.execute(
+            "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+            params![tag],
+        )?;
+        db.execute(
+            "INSERT OR IGNORE INTO book_tags (book_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2",
+            params![book_id, tag],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn set_cover(db: &Connection, book_id: &str, url: &str, image: &[u8]) -> Result<()> {
+    db.execute(
+        "INSERT OR REPLACE INTO covers (book_id, url, image) VALUES (?1, ?2, ?3)",
+        params![book_id, url, image],
+    )?;
+
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct BookQuery {
+    /// Only list books in this series
+    #[arg(long)]
+    pub series: Option<String>,
+
+    /// Only list books with a contributor whose name contains this
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// Only list books with this tag, at any level of its category path
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Only list the book with this ISBN-13
+    #[arg(long)]
+    pub isbn: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CatalogueEntry {
+    pub id: String,
+    pub title: String,
+    pub authors: String,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+}
+
+impl CatalogueEntry {
+    pub fn describe(&self) -> String {
+        let series = match (&self.series, self.series_index) {
+            (Some(series), Some(index)) => format!(" ({} #{})", series, index),
+            (Some(series), None) => format!(" ({})", series),
+            _ => String::new(),
+        };
+
+        format!("{} — {}{} [{}]", self.title, self.authors, series, self.id)
+    }
+}
+
+/// Escapes `%`, `_` and `\` for a LIKE pattern with `ESCAPE '\'`
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Lists the catalogued books matching every given criterion, in series order
+pub fn query_books(db: &Connection, query: &BookQuery) -> Result<Vec<CatalogueEntry>> {
+    let mut statement = db.prepare(
+        "SELECT books.id, books.title, series.name, books.series_index,
+            (SELECT group_concat(name, ' & ') FROM (
+                SELECT contributors.name FROM book_contributors
+                JOIN contributors ON contributors.id = book_contributors.contributor_id
+                WHERE book_contributors.book_id = books.id AND book_contributors.role = 'author'
+                ORDER BY book_contributors.position
+            ))
+        FROM books
+        LEFT JOIN series ON series.id = books.series_id
+        WHERE (?1 IS NULL OR series.name = ?1)
+            AND (?2 IS NULL OR EXISTS (
+                SELECT 1 FROM book_contributors
+                JOIN contributors ON contributors.id = book_contributors.contributor_id
+                WHERE book_contributors.book_id = books.id
+                    AND contributors.name LIKE '%' || ?2 || '%' ESCAPE '\\'
+            ))
+            AND (?3 IS NULL OR EXISTS (
+                SELECT 1 FROM book_tags
+                JOIN tags ON tags.id = book_tags.tag_id
+                WHERE book_tags.book_id = books.id
+                    AND '.' || tags.name || '.' LIKE '%.' || ?3 || '.%' ESCAPE '\\'
+            ))
+            AND (?4 IS NULL OR books.isbn = ?4)
+        ORDER BY series.name, books.series_index, books.title",
+    )?;
+    let entries = statement
+        .query_map(
+            params![
+                query.series,
+                query.author.as_deref().map(escape_like),
+                query.tag.as_deref().map(escape_like),
+                query.isbn
+            ],
+            |row| {
+                Ok(CatalogueEntry {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    series: row.get(2)?,
+                    series_index: row.get(3)?,
+                    authors: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                })
+            },
+        )?
+        .collect::<Result<Vec<CatalogueEntry>, rusqlite::Error>>()?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::BookKind;
+
+    fn add_book(db: &Connection, id: &str, title: &str, series_index: f64) -> Result<()> {
+        let series_id = upsert_series(db, "Mistborn")?;
+        db.execute(
+            "INSERT INTO books (id, kind, title, series_id, series_index, updated_at)
+            VALUES (?1, 'ebook', ?2, ?3, ?4, '2024-06-01T00:00:00Z')
+            ON CONFLICT (id) DO UPDATE SET title = excluded.title",
+            params![id, title, series_id, series_index],
+        )?;
+        set_contributors(db, id, "author", &["Brandon Sanderson"])?;
+        set_tags(db, id, &["Fiction & Literature.Fantasy".to_string()])?;
+        set_cover(db, id, "https://cdn.kobo.com/cover.jpg", &[0xFF, 0xD8])
+    }
+
+    #[test]
+    fn migrate_catalogue() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        migrate(&mut db)?;
+        migrate(&mut db)?;
+        let version: usize = db.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        assert_eq!(version, MIGRATIONS.len());
+        Ok(())
+    }
+
+    #[test]
+    fn query_series() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        migrate(&mut db)?;
+        add_book(&db, "the-well-of-ascension", "The Well of Ascension", 2.0)?;
+        add_book(&db, "mistborn-3", "Mistborn", 1.0)?;
+        add_book(&db, "mistborn-3", "The Final Empire", 1.0)?;
+
+        let books = query_books(
+            &db,
+            &BookQuery {
+                series: Some("mistborn".to_string()),
+                tag: Some("Fantasy".to_string()),
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(
+            books
+                .iter()
+                .map(|book| book.title.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["The Final Empire", "The Well of Ascension"]
+        );
+        assert_eq!(books[0].authors, "Brandon Sanderson");
+        let cover: Vec<u8> = db.query_row(
+            "SELECT image FROM covers WHERE book_id = 'mistborn-3'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(cover, vec![0xFF, 0xD8]);
+        Ok(())
+    }
+
+    #[test]
+    fn query_without_wildcards() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        migrate(&mut db)?;
+        upsert_book(&db, &Metadata::sample(), &[0xFF, 0xD8], &Config::default())?;
+        let by_author = |author: &str| {
+            query_books(
+                &db,
+                &BookQuery {
+                    author: Some(author.to_string()),
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(by_author("Sanderson")?.len(), 1);
+        assert!(by_author("%")?.is_empty());
+        assert!(by_author("Brandon_Sanderson")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn audiobook_keys() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        migrate(&mut db)?;
+        let ebook = Metadata::sample();
+        let audiobook = Metadata::sample().with_kind(BookKind::Audiobook);
+        upsert_book(&db, &ebook, &[0xFF, 0xD8], &Config::default())?;
+        upsert_book(&db, &audiobook, &[0xFF, 0xD8], &Config::default())?;
+
+        let mut statement = db.prepare("SELECT id, kind FROM books ORDER BY id")?;
+        let books = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(String, String)>, rusqlite::Error>>()?;
+
+        assert_eq!(
+            books,
+            vec![
+                ("audiobook/mistborn-3".to_string(), "audiobook".to_string()),
+                ("mistborn-3".to_string(), "ebook".to_string())
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,441 @@
+use crate::{
+    book::Metadata,
+    config::Config,
+    csv_file::write_atomically,
+    filter::BookKind,
+    name::split_name,
+    output::{OutputBook, OutputWriter},
+};
+use anyhow::Result;
+use chrono::Datelike;
+use indicatif::ProgressBar;
+use serde_json::{json, Value};
+use std::{fs::read_to_string, path::Path};
+
+const BIBTEX_FILE_PATH: &str = "./citations.bib";
+const RIS_FILE_PATH: &str = "./citations.ris";
+const CSL_JSON_FILE_PATH: &str = "./citations.json";
+
+/// Adds BibTeX `@book` entries to ./citations.bib, replacing entries with the same URL
+pub fn bibtex_writer() -> CitationFileWriter {
+    CitationFileWriter {
+        path: BIBTEX_FILE_PATH,
+        entry_start: "@",
+        url_field: ("url = {", "},"),
+        to_entry: to_bibtex,
+        entries: None,
+    }
+}
+
+/// Adds RIS records to ./citations.ris, replacing records with the same URL
+pub fn ris_writer() -> CitationFileWriter {
+    CitationFileWriter {
+        path: RIS_FILE_PATH,
+        entry_start: "TY  - ",
+        url_field: ("UR  - ", ""),
+        to_entry: to_ris,
+        entries: None,
+    }
+}
+
+/// Rewrites a BibTeX or RIS file once per batch. The URL of each entry identifies its
+/// book, as the ID does in CSL-JSON, so books written again replace their old entries.
+pub struct CitationFileWriter {
+    path: &'static str,
+    /// Start of the first line of an entry
+    entry_start: &'static str,
+    /// Text around the URL on its line
+    url_field: (&'static str, &'static str),
+    to_entry: fn(&Metadata) -> String,
+    entries: Option<Vec<String>>,
+}
+
+impl CitationFileWriter {
+    fn entry_url<'a>(&self, entry: &'a str) -> Option<&'a str> {
+        let (prefix, suffix) = self.url_field;
+        entry.lines().find_map(|line| {
+            line.trim()
+                .strip_prefix(prefix)
+                .map(|url| url.strip_suffix(suffix).unwrap_or(url))
+        })
+    }
+}
+
+impl OutputWriter for CitationFileWriter {
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        let path = Path::new(self.path);
+        let content = match path.exists() {
+            true => read_to_string(path)?,
+            false => String::new(),
+        };
+        let mut entries = Vec::<String>::new();
+        for line in content.lines() {
+            match entries.last_mut() {
+                Some(entry) if !line.starts_with(self.entry_start) => {
+                    entry.push_str(line);
+                    entry.push('\n');
+                }
+                _ => entries.push(format!("{}\n", line)),
+            }
+        }
+        self.entries = Some(entries);
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, _config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some(mut entries) = self.entries.take() else {
+            anyhow::bail!("The writer of {} was not begun", self.path);
+        };
+        let entry = (self.to_entry)(book.metadata);
+        pb.inc(1);
+
+        let url = book.metadata.url();
+        entries.retain(|existing_entry| self.entry_url(existing_entry) != Some(url.as_str()));
+        entries.push(entry);
+        self.entries = Some(entries);
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        if let Some(entries) = self.entries.take() {
+            let content = entries
+                .iter()
+                .map(|entry| format!("{}\n\n", entry.trim_end_matches('\n')))
+                .collect::<String>();
+            write_atomically(Path::new(self.path), content.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds CSL-JSON items to the array in ./citations.json, replacing items with the same ID
+#[derive(Default)]
+pub struct CslJsonWriter {
+    items: Option<Vec<Value>>,
+}
+
+impl OutputWriter for CslJsonWriter {
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        let path = Path::new(CSL_JSON_FILE_PATH);
+        self.items = Some(match path.exists() {
+            true => serde_json::from_str::<Vec<Value>>(&read_to_string(path)?)?,
+            false => Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, _config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some(items) = self.items.as_mut() else {
+            anyhow::bail!("The CSL-JSON writer was not begun");
+        };
+        let item = to_csl_json(book.metadata);
+        pb.inc(1);
+
+        items.retain(|existing_item| existing_item["id"] != item["id"]);
+        items.push(item);
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        if let Some(items) = self.items.take() {
+            write_atomically(
+                Path::new(CSL_JSON_FILE_PATH),
+                serde_json::to_string_pretty(&items)?.as_bytes(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Title and subtitle as cited, e.g. `Mistborn: The Final Empire`
+fn full_title(metadata: &Metadata) -> String {
+    match metadata.subtitle() {
+        Some(subtitle) => format!("{}: {}", metadata.title(), subtitle),
+        None => metadata.title().to_string(),
+    }
+}
+
+fn to_bibtex(metadata: &Metadata) -> String {
+    let authors = metadata
+        .authors()
+        .iter()
+        .map(|author| match split_name(author) {
+            Some((given_names, family_name)) => format!(
+                "{}, {}",
+                escape_bibtex(family_name),
+                escape_bibtex(given_names)
+            ),
+            // Braces keep BibTeX from splitting names it can't parse
+            None => format!("{{{}}}", escape_bibtex(author)),
+        })
+        .collect::<Vec<String>>()
+        .join(" and ");
+    let year = metadata.release_date().map(|date| date.year().to_string());
+    let mut fields = vec![
+        ("author", authors),
+        ("title", escape_bibtex(&full_title(metadata))),
+    ];
+    if let Some(series) = metadata.series_name() {
+        fields.push(("series", escape_bibtex(series)));
+    }
+    if let Some(index) = metadata.series_index() {
+        fields.push(("volume", index.to_string()));
+    }
+    if !metadata.publisher().is_empty() {
+        fields.push(("publisher", escape_bibtex(metadata.publisher())));
+    }
+    if let Some(year) = &year {
+        fields.push(("year", year.clone()));
+    }
+    if let Some(isbn) = metadata.isbn() {
+        fields.push(("isbn", isbn.isbn_13.clone()));
+    }
+    if !metadata.language_code().is_empty() {
+        fields.push(("language", metadata.language_code().to_string()));
+    }
+    fields.push(("url", metadata.url()));
+
+    let fields = fields
+        .iter()
+        .map(|(name, value)| format!("  {} = {{{}}},", name, value))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!(
+        "@book{{{},\n{}\n}}\n",
+        citation_key(metadata, year.as_deref()),
+        fields
+    )
+}
+
+/// Key such as `sanderson2010mistborn`, or the Kobo ID for names and titles without
+/// ASCII letters
+fn citation_key(metadata: &Metadata, year: Option<&str>) -> String {
+    let ascii_word = |text: &str| {
+        text.split_whitespace()
+            .map(|word| {
+                word.chars()
+                    .filter(char::is_ascii_alphanumeric)
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .find(|word| !word.is_empty())
+    };
+    let family_name = metadata
+        .authors()
+        .first()
+        .and_then(|author| split_name(author))
+        .and_then(|(_, family_name)| ascii_word(family_name));
+    let title_word = ascii_word(metadata.title());
+
+    match (family_name, title_word) {
+        (Some(family_name), Some(title_word)) => {
+            format!("{}{}{}", family_name, year.unwrap_or_default(), title_word)
+        }
+        _ => metadata
+            .book_id()
+            .chars()
+            .filter(|char| char.is_ascii_alphanumeric() || *char == '-')
+            .collect(),
+    }
+}
+
+fn escape_bibtex(text: &str) -> String {
+    text.chars()
+        .map(|char| match char {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => format!("\\{}", char),
+            '\\' => "\\textbackslash{}".to_string(),
+            '~' => "\\textasciitilde{}".to_string(),
+            '^' => "\\textasciicircum{}".to_string(),
+            char => char.to_string(),
+        })
+        .collect()
+}
+
+fn to_ris(metadata: &Metadata) -> String {
+    let mut tags = vec![(
+        "TY",
+        match metadata.kind() {
+            BookKind::Ebook => "EBOOK".to_string(),
+            BookKind::Audiobook => "SOUND".to_string(),
+        },
+    )];
+    for author in metadata.authors() {
+        let author = match split_name(author) {
+            Some((given_names, family_name)) => format!("{}, {}", family_name, given_names),
+            None => author.to_string(),
+        };
+        tags.push(("AU", author));
+    }
+    tags.push(("TI", full_title(metadata)));
+    if let Some(series) = metadata.series_name() {
+        tags.push(("T3", series.to_string()));
+    }
+    if !metadata.publisher().is_empty() {
+        tags.push(("PB", metadata.publisher().to_string()));
+    }
+    if let Some(release_date) = metadata.release_date() {
+        tags.push(("PY", release_date.year().to_string()));
+        tags.push(("DA", release_date.format("%Y/%m/%d").to_string()));
+    }
+    if let Some(isbn) = metadata.isbn() {
+        tags.push(("SN", isbn.isbn_13.clone()));
+    }
+    if !metadata.language_code().is_empty() {
+        tags.push(("LA", metadata.language_code().to_string()));
+    }
+    tags.push(("UR", metadata.url()));
+    tags.push(("ER", String::new()));
+
+    tags.iter()
+        .map(|(tag, value)| format!("{}  - {}\n", tag, value))
+        .collect()
+}
+
+fn to_csl_json(metadata: &Metadata) -> Value {
+    let authors = metadata
+        .authors()
+        .iter()
+        .map(|author| match split_name(author) {
+            Some((given_names, family_name)) => json!({
+                "family": family_name,
+                "given": given_names,
+            }),
+            None => json!({ "literal": author }),
+        })
+        .collect::<Vec<Value>>();
+    let mut item = json!({
+        "id": metadata.book_id(),
+        "type": "book",
+        "title": full_title(metadata),
+        "author": authors,
+        "URL": metadata.url(),
+    });
+    if let Some(series) = metadata.series_name() {
+        item["collection-title"] = json!(series);
+    }
+    if let Some(index) = metadata.series_index() {
+        item["collection-number"] = json!(index.to_string());
+    }
+    if !metadata.publisher().is_empty() {
+        item["publisher"] = json!(metadata.publisher());
+    }
+    if let Some(release_date) = metadata.release_date() {
+        item["issued"] = json!({
+            "date-parts": [[release_date.year(), release_date.month(), release_date.day()]]
+        });
+    }
+    if let Some(isbn) = metadata.isbn() {
+        item["ISBN"] = json!(isbn.isbn_13);
+    }
+    if !metadata.language_code().is_empty() {
+        item["language"] = json!(metadata.language_code());
+    }
+
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bibtex_entry() {
+        let entry = to_bibtex(&Metadata::sample());
+
+        assert_eq!(
+            entry,
+            "@book{sanderson2010mistborn,
+  author = {Sanderson, Brandon},
+  title = {Mistborn: The Final Empire},
+  series = {Mistborn},
+  volume = {1},
+  publisher = {Tor Publishing Group},
+  year = {2010},
+  isbn = {9781429989817},
+  language = {en},
+  url = {https://www.kobo.com/tw/zh/ebook/mistborn-3},
+}
+"
+        )
+    }
+
+    #[test]
+    fn replace_ris_records() -> Result<()> {
+        let path = std::env::temp_dir().join("kobo-books-metadata-downloader-citations.ris");
+        let _ = std::fs::remove_file(&path);
+        let mut writer = CitationFileWriter {
+            path: Box::leak(path.to_string_lossy().into_owned().into_boxed_str()),
+            ..ris_writer()
+        };
+        let (config, pb) = (Config::default(), ProgressBar::hidden());
+        let ebook = Metadata::sample();
+        let audiobook = Metadata::sample().with_kind(BookKind::Audiobook);
+
+        for books in [vec![&ebook], vec![&ebook, &audiobook, &ebook]] {
+            writer.begin(&config)?;
+            for book in books {
+                writer.write(&OutputBook::new(book), &config, &pb)?;
+            }
+            writer.finish(&config)?;
+        }
+
+        let content = read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(
+            content,
+            format!("{}\n{}\n", to_ris(&audiobook), to_ris(&ebook))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn escape_bibtex_specials() {
+        let escaped = escape_bibtex(r"50% {off} C:\~^_");
+
+        assert_eq!(
+            escaped,
+            r"50\% \{off\} C:\textbackslash{}\textasciitilde{}\textasciicircum{}\_"
+        )
+    }
+
+    #[test]
+    fn ris_record() {
+        let record = to_ris(&Metadata::sample());
+
+        assert_eq!(
+            record,
+            "TY  - EBOOK
+AU  - Sanderson, Brandon
+TI  - Mistborn: The Final Empire
+T3  - Mistborn
+PB  - Tor Publishing Group
+PY  - 2010
+DA  - 2010/04/01
+SN  - 9781429989817
+LA  - en
+UR  - https://www.kobo.com/tw/zh/ebook/mistborn-3
+ER  - \n"
+        )
+    }
+
+    #[test]
+    fn csl_json_item() {
+        let item = to_csl_json(&Metadata::sample());
+
+        assert_eq!(
+            item["author"],
+            json!([{"family": "Sanderson", "given": "Brandon"}])
+        );
+        assert_eq!(item["issued"], json!({"date-parts": [[2010, 4, 1]]}));
+        assert_eq!(item["ISBN"], "9781429989817")
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{config::Config, date::date_format_label};
+use clap::ValueEnum;
+use std::{fs::File, io::Read};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Column {
+    Id,
+    Title,
+    Subtitle,
+    Authors,
+    Series,
+    SeriesIndex,
+    Cover,
+    Synopsis,
+    Tags,
+    Publisher,
+    ReleaseDate,
+    LanguageCode,
+    LanguageTag,
+    Isbn,
+    #[value(name = "isbn-10")]
+    Isbn10,
+    ProductId,
+    PageCount,
+    WordCount,
+    ReadingTime,
+    Rating,
+    RatingCount,
+    ReviewCount,
+    Price,
+    SalePrice,
+    Currency,
+    FileFormat,
+    Drm,
+    AgeRating,
+    Kind,
+    Narrators,
+    Duration,
+    Abridged,
+}
+
+impl Column {
+    pub const ALL: [Column; 32] = [
+        Column::Id,
+        Column::Title,
+        Column::Subtitle,
+        Column::Authors,
+        Column::Series,
+        Column::SeriesIndex,
+        Column::Cover,
+        Column::Synopsis,
+        Column::Tags,
+        Column::Publisher,
+        Column::ReleaseDate,
+        Column::LanguageCode,
+        Column::LanguageTag,
+        Column::Isbn,
+        Column::Isbn10,
+        Column::ProductId,
+        Column::PageCount,
+        Column::WordCount,
+        Column::ReadingTime,
+        Column::Rating,
+        Column::RatingCount,
+        Column::ReviewCount,
+        Column::Price,
+        Column::SalePrice,
+        Column::Currency,
+        Column::FileFormat,
+        Column::Drm,
+        Column::AgeRating,
+        Column::Kind,
+        Column::Narrators,
+        Column::Duration,
+        Column::Abridged,
+    ];
+
+    pub fn header(self, config: &Config) -> String {
+        match self {
+            Column::Id => "ID".to_string(),
+            Column::Title => "Title".to_string(),
+            Column::Subtitle => "Subtitle".to_string(),
+            Column::Authors => "Author(s)".to_string(),
+            Column::Series => "Series".to_string(),
+            Column::SeriesIndex => "Series Index".to_string(),
+            Column::Cover => "Cover Path".to_string(),
+            Column::Synopsis => format!("Synopsis ({})", config.synopsis_format.label()),
+            Column::Tags => "Tag(s)".to_string(),
+            Column::Publisher => "Publisher".to_string(),
+            Column::ReleaseDate => {
+                format!("Release Date ({})", date_format_label(&config.date_format))
+            }
+            Column::LanguageCode => "Language Code (ISO 639-1)".to_string(),
+            Column::LanguageTag => "Language Tag (BCP 47)".to_string(),
+            Column::Isbn => "ISBN".to_string(),
+            Column::Isbn10 => "ISBN-10".to_string(),
+            Column::ProductId => "Kobo Product ID".to_string(),
+            Column::PageCount => "Pages".to_string(),
+            Column::WordCount => "Words".to_string(),
+            Column::ReadingTime => "Reading Time (hours)".to_string(),
+            Column::Rating => "Rating".to_string(),
+            Column::RatingCount => "Rating Count".to_string(),
+            Column::ReviewCount => "Review Count".to_string(),
+            Column::Price => "Price".to_string(),
+            Column::SalePrice => "Sale Price".to_string(),
+            Column::Currency => "Currency".to_string(),
+            Column::FileFormat => "File Format".to_string(),
+            Column::Drm => "DRM".to_string(),
+            Column::AgeRating => "Age Rating".to_string(),
+            Column::Kind => "Kind".to_string(),
+            Column::Narrators => "Narrator(s)".to_string(),
+            Column::Duration => "Duration (minutes)".to_string(),
+            Column::Abridged => "Abridged".to_string(),
+        }
+    }
+}
+
+/// CSV columns in order, read from a headerless CSV file of `column,header` rows, e.g.
+/// `authors,Authors`. An empty or missing header keeps the default one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnSpec(Vec<(Column, Option<String>)>);
+
+impl ColumnSpec {
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|error| format!("{}: {}", path, error))?;
+
+        Self::from_reader(file).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self, String> {
+        let columns = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader)
+            .records()
+            .map(|record| {
+                let record = record.map_err(|error| error.to_string())?;
+                let name = record.get(0).unwrap_or_default().trim();
+                let column = Column::from_str(name, true)
+                    .map_err(|_| format!("Unknown column: {}", name))?;
+                let header = record
+                    .get(1)
+                    .map(str::trim)
+                    .filter(|header| !header.is_empty())
+                    .map(str::to_string);
+
+                Ok((column, header))
+            })
+            .collect::<Result<Vec<(Column, Option<String>)>, String>>()?;
+
+        Ok(Self(columns))
+    }
+
+    pub fn columns(&self) -> &[(Column, Option<String>)] {
+        &self.0
+    }
+}
+
+/// Parses a CSV delimiter: a single ASCII character, or `tab`
+pub fn parse_delimiter(delimiter: &str) -> Result<u8, String> {
+    match delimiter {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if delimiter.len() == 1 && delimiter.is_ascii() => Ok(delimiter.as_bytes()[0]),
+        _ => Err(format!("Not a single ASCII character: {}", delimiter)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_column_spec() -> Result<(), String> {
+        let column_spec =
+            ColumnSpec::from_reader("title,Title\nauthors, Authors \nisbn\nisbn-10,\n".as_bytes())?;
+
+        assert_eq!(
+            column_spec.columns(),
+            [
+                (Column::Title, Some("Title".to_string())),
+                (Column::Authors, Some("Authors".to_string())),
+                (Column::Isbn, None),
+                (Column::Isbn10, None),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_delimiters() {
+        assert_eq!(parse_delimiter(";"), Ok(b';'));
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert!(parse_delimiter("::").is_err())
+    }
+}
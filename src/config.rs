@@ -0,0 +1,152 @@
+use crate::{
+    column::{parse_delimiter, Column, ColumnSpec},
+    date::{validate_date_format, DEFAULT_DATE_FORMAT},
+    synopsis::SynopsisFormat,
+    tag::{parse_tag_separator, TagMap},
+    template::TemplateMode,
+};
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Append rows to ./metadata.csv
+    #[default]
+    Csv,
+    /// Append JSON Lines to ./metadata.jsonl
+    Json,
+    /// Add or update books in the Calibre library given by --calibre-library
+    Calibre,
+    /// Write an OPF file per book to ./opf, as Calibre and other e-book managers import
+    Opf,
+    /// Add or update books in the ./metadata.sqlite catalogue
+    Sqlite,
+    /// Append rows to ./goodreads.csv for a Goodreads import, shelved as to-read
+    Goodreads,
+    /// Append rows to ./storygraph.csv for a StoryGraph import, marked as to-read
+    Storygraph,
+    /// Add BibTeX entries to ./citations.bib, replacing those of the same books
+    Bibtex,
+    /// Add RIS records to ./citations.ris, replacing those of the same books
+    Ris,
+    /// Add CSL-JSON items to ./citations.json, replacing those of the same books
+    CslJson,
+    /// Append binary MARC 21 records to ./metadata.mrc
+    Marc,
+    /// Add MARCXML records to ./metadata.marcxml
+    Marcxml,
+    /// Add Dublin Core records to ./metadata.dc.xml
+    DublinCore,
+    /// Add ONIX 3.0 products to ./metadata.onix.xml
+    Onix,
+    /// Render --template once per book, or once per batch with --template-mode batch
+    Template,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct Config {
+    /// Comma-separated output formats of the scraped metadata, all written from one scrape
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [OutputFormat::Csv])]
+    pub format: Vec<OutputFormat>,
+
+    /// Output format of release dates, in strftime syntax
+    #[arg(long, default_value = DEFAULT_DATE_FORMAT, value_parser = validate_date_format)]
+    pub date_format: String,
+
+    /// Output format of synopses
+    #[arg(long, value_enum, default_value_t)]
+    pub synopsis_format: SynopsisFormat,
+
+    /// Keep only the most specific category of each Kobo category path
+    #[arg(long)]
+    pub leaf_tags: bool,
+
+    /// Headerless CSV file of `from,to` category renames; an empty `to` drops the category
+    #[arg(long, value_parser = TagMap::from_path)]
+    pub tag_map: Option<TagMap>,
+
+    /// Comma-separated columns to write to the CSV file, in order
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = Column::ALL)]
+    pub columns: Vec<Column>,
+
+    /// Headerless CSV file of `column,header` rows giving the CSV columns, their order and
+    /// their headers, e.g. Calibre's import names; replaces --columns
+    #[arg(long, value_parser = ColumnSpec::from_path, conflicts_with = "columns")]
+    pub column_spec: Option<ColumnSpec>,
+
+    /// Delimiter of the CSV file: a single character, or `tab`
+    #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+    pub csv_delimiter: u8,
+
+    /// Start a new CSV file with a UTF-8 byte order mark, for Excel
+    #[arg(long)]
+    pub csv_bom: bool,
+
+    /// Separator between authors, and between narrators, in the CSV file
+    #[arg(long, default_value = "&")]
+    pub list_separator: String,
+
+    /// Separator between tags in the CSV file
+    #[arg(long, default_value = ",", value_parser = parse_tag_separator)]
+    pub tag_separator: String,
+
+    /// Calibre library folder, containing metadata.db, to write into with `--format calibre`
+    #[arg(long)]
+    pub calibre_library: Option<PathBuf>,
+
+    /// Jinja-style template to render with `--format template`
+    #[arg(long)]
+    pub template: Option<PathBuf>,
+
+    /// Path of each rendered file inside the current folder, itself a template, e.g.
+    /// `content/books/{{ book.title | slug }}.md`
+    #[arg(long)]
+    pub template_output: Option<String>,
+
+    /// Render the template once per book, or once for the whole batch
+    #[arg(long, value_enum, default_value_t)]
+    pub template_mode: TemplateMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            format: vec![OutputFormat::default()],
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            synopsis_format: SynopsisFormat::default(),
+            leaf_tags: false,
+            tag_map: None,
+            columns: Column::ALL.to_vec(),
+            column_spec: None,
+            csv_delimiter: b',',
+            csv_bom: false,
+            list_separator: "&".to_string(),
+            tag_separator: ",".to_string(),
+            calibre_library: None,
+            template: None,
+            template_output: None,
+            template_mode: TemplateMode::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Columns of the CSV file in order, with their headers
+    pub fn csv_columns(&self) -> Vec<(Column, String)> {
+        match &self.column_spec {
+            Some(column_spec) => column_spec
+                .columns()
+                .iter()
+                .map(|(column, header)| {
+                    let header = header.clone().unwrap_or_else(|| column.header(self));
+                    (*column, header)
+                })
+                .collect(),
+            None => self
+                .columns
+                .iter()
+                .map(|column| (*column, column.header(self)))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::filter::BookKind;
+use anyhow::Result;
+use scraper::{Html, Selector};
+
+pub const KOBO_ORIGIN: &str = "https://www.kobo.com";
+const MAX_PAGES: usize = 100;
+
+/// Collects the IDs of every book of a kind listed on a Kobo list page (series, author,
+/// search results…), following its pagination
+pub fn crawl_book_ids(url: &str, kind: BookKind) -> Result<Vec<String>> {
+    let mut book_ids = Vec::<String>::new();
+    let mut next_page_url = Some(to_absolute_url(url));
+
+    for _ in 0..MAX_PAGES {
+        let Some(page_url) = next_page_url.take() else {
+            break;
+        };
+        let page_html = reqwest::blocking::get(&page_url)?.text()?;
+        let page = Html::parse_document(&page_html);
+
+        let new_book_ids = page
+            .get_listed_book_ids(kind)
+            .into_iter()
+            .filter(|book_id| !book_ids.contains(book_id))
+            .collect::<Vec<String>>();
+        if new_book_ids.is_empty() {
+            break;
+        }
+        book_ids.extend(new_book_ids);
+
+        next_page_url = page.get_next_page_url();
+    }
+
+    Ok(book_ids)
+}
+
+pub fn to_absolute_url(href: &str) -> String {
+    match href.starts_with('/') {
+        true => format!("{}{}", KOBO_ORIGIN, href),
+        false => href.to_string(),
+    }
+}
+
+pub trait ListPageHtml {
+    fn get_listed_book_ids(&self, kind: BookKind) -> Vec<String>;
+    fn get_next_page_url(&self) -> Option<String>;
+}
+
+impl ListPageHtml for Html {
+    fn get_listed_book_ids(&self, kind: BookKind) -> Vec<String> {
+        let book_link_selector = Selector::parse("h2.title a, p.title a, .item-detail .title a")
+            .expect("Invalid selector");
+        let mut book_ids = Vec::<String>::new();
+        for book_id in self
+            .select(&book_link_selector)
+            .filter_map(|a| a.value().attr("href"))
+            .filter(|href| href.contains(kind.path_segment()))
+            .filter_map(|href| {
+                href.split(['?', '#'])
+                    .next()
+                    .and_then(|path| path.rsplit_once('/'))
+                    .map(|(_, book_id)| book_id)
+                    .filter(|book_id| !book_id.is_empty())
+                    .map(|book_id| kind.to_book_id(book_id))
+            })
+        {
+            if !book_ids.contains(&book_id) {
+                book_ids.push(book_id);
+            }
+        }
+
+        book_ids
+    }
+
+    fn get_next_page_url(&self) -> Option<String> {
+        let next_page_selector =
+            Selector::parse("link[rel='next'], a[rel='next'], a.page-link.next, li.next > a")
+                .expect("Invalid selector");
+        let next_page_url = self
+            .select(&next_page_selector)
+            .find_map(|element| element.value().attr("href"))
+            .map(to_absolute_url);
+
+        next_page_url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_page_book_ids() {
+        let list_page = Html::parse_document(
+            r#"<h2 class="title"><a href="/tw/zh/ebook/mistborn-3">Mistborn</a></h2>
+            <h2 class="title"><a href="https://www.kobo.com/tw/zh/ebook/the-well-of-ascension">The Well of Ascension</a></h2>
+            <p class="title"><a href="/tw/zh/ebook/mistborn-3">Mistborn</a></p>
+            <h2 class="title"><a href="/tw/zh/audiobook/mistborn-1">Mistborn</a></h2>
+            <a class="page-link next" href="/tw/zh/series/mistborn?pageNumber=2">Next</a>"#,
+        );
+
+        assert_eq!(
+            list_page.get_listed_book_ids(BookKind::Audiobook),
+            vec!["audiobook/mistborn-1".to_string()]
+        );
+        assert_eq!(
+            list_page.get_listed_book_ids(BookKind::Ebook),
+            vec![
+                "mistborn-3".to_string(),
+                "the-well-of-ascension".to_string()
+            ]
+        );
+        assert_eq!(
+            list_page.get_next_page_url(),
+            Some("https://www.kobo.com/tw/zh/series/mistborn?pageNumber=2".to_string())
+        )
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{column::Column, date::parse_release_date};
+use anyhow::{anyhow, Result};
+use std::{
+    fs::{rename, File, OpenOptions, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// Headers written by earlier versions, with the column now written in their place
+const LEGACY_HEADERS: &[(&str, Column)] = &[("Release Date (yyyy-m-d)", Column::ReleaseDate)];
+
+/// The lock file next to a CSV file, released when dropped. The file itself stays, as
+/// removing it while locked would let two runs lock different files at the same path.
+pub struct CsvLock {
+    _lock_file: File,
+}
+
+/// Takes the lock file next to a CSV file, so that a second run fails instead of
+/// interleaving its rows
+pub fn lock_csv_file(path: &Path) -> Result<CsvLock> {
+    let lock_path = with_extension_suffix(path, "lock");
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+    match lock_file.try_lock() {
+        Ok(()) => Ok(CsvLock {
+            _lock_file: lock_file,
+        }),
+        Err(TryLockError::WouldBlock) => Err(anyhow!(
+            "{} is being written by another run (locked by {})",
+            path.display(),
+            lock_path.display()
+        )),
+        Err(TryLockError::Error(error)) => Err(error.into()),
+    }
+}
+
+/// Replaces the content of a file by writing it to a temporary file first, so that a
+/// crash leaves either the old or the new content
+pub fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp_path = with_extension_suffix(path, "tmp");
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(content)?;
+    tmp_file.sync_all()?;
+    rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Appends a whole row to an open file in one write and syncs it before the next one. A
+/// row cut short by a crash is removed by `strip_partial_row` on the next run.
+pub fn append_row(file: &mut File, row: &[u8]) -> Result<()> {
+    file.write_all(row)?;
+    file.sync_data()?;
+
+    Ok(())
+}
+
+/// Cuts a CSV file after its last complete row, dropping a row that a crash left
+/// unfinished. Returns `None` when the file ends with a complete row.
+pub fn strip_partial_row(content: &[u8]) -> Option<&[u8]> {
+    let mut quoted = false;
+    let mut complete_len = 0;
+    for (index, byte) in content.iter().enumerate() {
+        match byte {
+            b'"' => quoted = !quoted,
+            b'\n' if !quoted => complete_len = index + 1,
+            _ => {}
+        }
+    }
+
+    (complete_len < content.len()).then(|| &content[..complete_len])
+}
+
+/// How the header of an existing CSV file compares with the expected one
+#[derive(Debug, PartialEq)]
+pub enum HeaderCheck {
+    Matches,
+    /// The file has a subset of the expected columns, possibly reordered, so its rows
+    /// can be rewritten under the expected header
+    NeedsMigration,
+}
+
+pub fn check_header(existing: &[String], expected: &[(Column, String)]) -> Result<HeaderCheck> {
+    if existing
+        .iter()
+        .eq(expected.iter().map(|(_, header)| header))
+    {
+        return Ok(HeaderCheck::Matches);
+    }
+
+    let unknown = existing
+        .iter()
+        .filter(|header| expected_position(header, expected).is_none())
+        .map(String::as_str)
+        .collect::<Vec<&str>>();
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "The CSV file has columns this run wouldn't write: {}. Write to another file, or pick the columns with --columns or --column-spec",
+            unknown.join(", ")
+        );
+    }
+
+    Ok(HeaderCheck::NeedsMigration)
+}
+
+/// Position in `expected` of the column under an existing header, which may be a legacy one
+fn expected_position(header: &str, expected: &[(Column, String)]) -> Option<usize> {
+    expected
+        .iter()
+        .position(|(_, expected)| expected == header)
+        .or_else(|| {
+            let (_, column) = LEGACY_HEADERS
+                .iter()
+                .find(|(legacy, _)| *legacy == header)?;
+            expected.iter().position(|(expected, _)| expected == column)
+        })
+}
+
+/// Formats one CSV row, with its line terminator
+pub fn to_csv_row<I, T>(record: I, delimiter: u8) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut csv_wtr = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+    csv_wtr.write_record(record)?;
+    let row = csv_wtr.into_inner()?;
+
+    Ok(row)
+}
+
+/// Reads the header of a CSV file, without its byte order mark
+pub fn read_header(content: &[u8], delimiter: u8) -> Result<Option<Vec<String>>> {
+    let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let header = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .from_reader(content)
+        .records()
+        .next()
+        .transpose()?
+        .map(|record| record.iter().map(str::to_string).collect());
+
+    Ok(header)
+}
+
+/// Rewrites every row of a CSV file under the expected header, leaving the new columns
+/// empty, reformatting dates under a legacy header and keeping any byte order mark
+pub fn migrate_rows(
+    content: &[u8],
+    delimiter: u8,
+    expected: &[(Column, String)],
+    date_format: &str,
+) -> Result<Vec<u8>> {
+    let bom = content.starts_with(UTF8_BOM);
+    let mut csv_rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(content.strip_prefix(UTF8_BOM).unwrap_or(content));
+    let existing = csv_rdr.headers()?.clone();
+    let sources = (0..expected.len())
+        .map(|index| {
+            existing
+                .iter()
+                .position(|header| expected_position(header, expected) == Some(index))
+        })
+        .collect::<Vec<Option<usize>>>();
+
+    let mut migrated = Vec::<u8>::new();
+    if bom {
+        migrated.extend(UTF8_BOM);
+    }
+    let mut csv_wtr = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(&mut migrated);
+    csv_wtr.write_record(expected.iter().map(|(_, header)| header))?;
+    for record in csv_rdr.records() {
+        let record = record?;
+        csv_wtr.write_record(
+            expected
+                .iter()
+                .zip(&sources)
+                .map(|((column, header), source)| {
+                    let Some(source) = *source else {
+                        return String::new();
+                    };
+                    let value = record.get(source).unwrap_or_default();
+                    match (column, existing.get(source) == Some(header.as_str())) {
+                        (Column::ReleaseDate, false) => parse_release_date(value)
+                            .map_or(value.to_string(), |date| {
+                                date.format(date_format).to_string()
+                            }),
+                        _ => value.to_string(),
+                    }
+                }),
+        )?;
+    }
+    drop(csv_wtr);
+
+    Ok(migrated)
+}
+
+fn with_extension_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, date::DEFAULT_DATE_FORMAT};
+
+    fn columns(columns: &[Column]) -> Vec<(Column, String)> {
+        let config = Config::default();
+        columns
+            .iter()
+            .map(|column| (*column, column.header(&config)))
+            .collect()
+    }
+
+    fn headers(headers: &[&str]) -> Vec<String> {
+        headers.iter().map(|header| header.to_string()).collect()
+    }
+
+    #[test]
+    fn check_headers() -> Result<()> {
+        let expected = columns(&[Column::Id, Column::Title, Column::Isbn]);
+
+        assert_eq!(
+            check_header(&headers(&["ID", "Title", "ISBN"]), &expected)?,
+            HeaderCheck::Matches
+        );
+        assert_eq!(
+            check_header(&headers(&["Title", "ID"]), &expected)?,
+            HeaderCheck::NeedsMigration
+        );
+        assert!(check_header(&headers(&["ID", "Notes"]), &expected).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn strip_partial_rows() {
+        let rows = b"ID,Synopsis\nmistborn-3,\"<p>line</p>\n<p>line</p>\"\n";
+
+        assert_eq!(strip_partial_row(rows), None);
+        assert_eq!(
+            strip_partial_row(b"ID,Synopsis\nmistborn-3,\"<p>line</p>\n"),
+            Some(&b"ID,Synopsis\n"[..])
+        );
+        assert_eq!(strip_partial_row(b"ID,Syn"), Some(&b""[..]))
+    }
+
+    #[test]
+    fn migrate_csv_rows() -> Result<()> {
+        let content = b"\xEF\xBB\xBFTitle;ID\nMistborn;mistborn-3\n";
+        let migrated = migrate_rows(
+            content,
+            b';',
+            &columns(&[Column::Id, Column::Title, Column::Isbn]),
+            DEFAULT_DATE_FORMAT,
+        )?;
+
+        assert_eq!(
+            read_header(&migrated, b';')?,
+            Some(headers(&["ID", "Title", "ISBN"]))
+        );
+        assert_eq!(
+            migrated,
+            b"\xEF\xBB\xBFID;Title;ISBN\nmistborn-3;Mistborn;\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_legacy_csv_rows() -> Result<()> {
+        // The header and a row as written by the first release
+        let content = "ID,Title,Subtitle,Author(s),Series,Series Index,Cover Path,Synopsis (HTML),Tag(s),Publisher,Release Date (yyyy-m-d),Language Code (ISO 639-1),ISBN\n\
+            silent-witch-1,Silent Witch,,依空まつり,,,./img/1.jpg,,,台灣角川,2022-5-27,zh,9786263524767\n";
+        let expected = columns(&Column::ALL);
+
+        assert_eq!(
+            check_header(
+                &read_header(content.as_bytes(), b',')?.unwrap_or_default(),
+                &expected
+            )?,
+            HeaderCheck::NeedsMigration
+        );
+        let migrated = migrate_rows(content.as_bytes(), b',', &expected, DEFAULT_DATE_FORMAT)?;
+        let mut csv_rdr = csv::Reader::from_reader(migrated.as_slice());
+        let release_date = csv_rdr
+            .records()
+            .next()
+            .transpose()?
+            .and_then(|record| record.get(10).map(str::to_string));
+
+        assert_eq!(release_date, Some("2022-05-27".to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::{
+    format::{Item, StrftimeItems},
+    NaiveDate,
+};
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+const MONTH_NAMES: [&[&str]; 12] = [
+    &[
+        "january", "janvier", "januar", "enero", "gennaio", "januari", "janeiro",
+    ],
+    &[
+        "february",
+        "février",
+        "februar",
+        "febrero",
+        "febbraio",
+        "februari",
+        "fevereiro",
+    ],
+    &["march", "mars", "märz", "marzo", "maart", "março"],
+    &["april", "avril", "abril", "aprile"],
+    &["may", "mai", "mayo", "maggio", "mei", "maio"],
+    &["june", "juin", "juni", "junio", "giugno", "junho"],
+    &["july", "juillet", "juli", "julio", "luglio", "julho"],
+    &["august", "août", "agosto", "augustus"],
+    &[
+        "september",
+        "septembre",
+        "septiembre",
+        "settembre",
+        "setembro",
+    ],
+    &[
+        "october", "octobre", "oktober", "octubre", "ottobre", "outubro",
+    ],
+    &["november", "novembre", "noviembre", "novembro"],
+    &[
+        "december",
+        "décembre",
+        "dezember",
+        "diciembre",
+        "dicembre",
+        "dezembro",
+    ],
+];
+
+pub fn parse_release_date(text: &str) -> Option<NaiveDate> {
+    let numbers = text
+        .split(|char: char| !char.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .collect::<Vec<&str>>();
+    let month = text
+        .split(|char: char| !char.is_alphabetic())
+        .find_map(month_from_name);
+
+    let (year, month, day) = match (month, numbers.as_slice()) {
+        (Some(month), [day, year, ..]) | (Some(month), [year, day, ..]) if year.len() == 4 => {
+            (year.parse().ok()?, month, day.parse().ok()?)
+        }
+        (None, [year, month, day, ..]) if year.len() == 4 => {
+            (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+        }
+        (None, [first, second, year, ..]) if year.len() == 4 => {
+            let first = first.parse().ok()?;
+            let second = second.parse().ok()?;
+            match second > 12 {
+                true => (year.parse().ok()?, first, second),
+                false => (year.parse().ok()?, second, first),
+            }
+        }
+        _ => return None,
+    };
+
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+fn month_from_name(word: &str) -> Option<u32> {
+    let word = word.to_lowercase();
+    if word.chars().count() < 3 {
+        return None;
+    }
+
+    MONTH_NAMES
+        .iter()
+        .position(|names| names.iter().any(|name| name.starts_with(&word)))
+        .map(|index| index as u32 + 1)
+}
+
+pub fn validate_date_format(format: &str) -> Result<String, String> {
+    let is_invalid = StrftimeItems::new(format).any(|item| item == Item::Error);
+    if is_invalid {
+        return Err(format!("Invalid date format: {}", format));
+    }
+
+    Ok(format.to_string())
+}
+
+pub fn date_format_label(format: &str) -> String {
+    [
+        ("%F", "yyyy-mm-dd"),
+        ("%Y", "yyyy"),
+        ("%y", "yy"),
+        ("%B", "mmmm"),
+        ("%b", "mmm"),
+        ("%m", "mm"),
+        ("%-m", "m"),
+        ("%d", "dd"),
+        ("%-d", "d"),
+    ]
+    .iter()
+    .fold(format.to_string(), |label, (specifier, placeholder)| {
+        label.replace(specifier, placeholder)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cjk_date() {
+        let date = parse_release_date("2022年5月27日");
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2022, 5, 27))
+    }
+
+    #[test]
+    fn parse_english_date() {
+        let date = parse_release_date("May 27, 2022");
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2022, 5, 27))
+    }
+
+    #[test]
+    fn parse_european_dates() {
+        let dates = [
+            "27 mai 2022",
+            "27. Mai 2022",
+            "27 de mayo de 2022",
+            "27/05/2022",
+        ]
+        .map(parse_release_date);
+
+        assert_eq!(dates, [NaiveDate::from_ymd_opt(2022, 5, 27); 4])
+    }
+
+    #[test]
+    fn parse_iso_date_time() {
+        let date = parse_release_date("2022-09-01T00:00:00Z");
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2022, 9, 1))
+    }
+
+    #[test]
+    fn parse_non_date() {
+        let date = parse_release_date("9781429989817");
+
+        assert_eq!(date, None)
+    }
+
+    #[test]
+    fn reject_invalid_date_format() {
+        assert!(validate_date_format("%Y-%m-%d").is_ok());
+        assert!(validate_date_format("%Q").is_err())
+    }
+
+    #[test]
+    fn label_date_format() {
+        assert_eq!(date_format_label(DEFAULT_DATE_FORMAT), "yyyy-mm-dd");
+        assert_eq!(date_format_label("%-d/%-m/%Y"), "d/m/yyyy")
+    }
+}
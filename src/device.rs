@@ -0,0 +1,228 @@
+use crate::{book::Metadata, config::Config, epub::EpubInfo, isbn::Isbn};
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use indicatif::ProgressBar;
+use rusqlite::{params, Connection};
+use std::{
+    fs::copy,
+    path::{Path, PathBuf},
+};
+
+const DATABASE_PATH: &str = ".kobo/KoboReader.sqlite";
+/// `ContentType` of whole books in the `content` table, as opposed to their chapters
+const BOOK_CONTENT_TYPE: u32 = 6;
+
+/// Fields of a `content` row to fill in from Kobo. `None` keeps the current value.
+#[derive(Debug, PartialEq, Default)]
+pub struct ContentUpdate {
+    pub series: Option<String>,
+    pub series_number: Option<String>,
+    pub description: Option<String>,
+    pub publisher: Option<String>,
+    pub language: Option<String>,
+}
+
+impl ContentUpdate {
+    pub fn from_metadata(metadata: &Metadata, config: &Config) -> Self {
+        let non_empty = |text: String| Some(text).filter(|text| !text.is_empty());
+
+        Self {
+            series: metadata.series_name().map(str::to_string),
+            series_number: metadata.series_index().map(|index| index.to_string()),
+            description: non_empty(metadata.synopsis(config.synopsis_format)),
+            publisher: non_empty(metadata.publisher().to_string()),
+            language: non_empty(metadata.language_code().to_string()),
+        }
+    }
+}
+
+/// Finds the database of a Kobo eReader from its mount point, or takes the path of a
+/// database file, e.g. a copy of it
+pub fn find_database(path: &Path) -> Result<PathBuf> {
+    let database_path = match path.is_dir() {
+        true => path.join(DATABASE_PATH),
+        false => path.to_path_buf(),
+    };
+    if !database_path.is_file() {
+        anyhow::bail!("{} not found", database_path.display());
+    }
+
+    Ok(database_path)
+}
+
+/// Copies the database next to itself, e.g. `KoboReader.sqlite.20240601-120000.bak`
+pub fn back_up_database(database_path: &Path) -> Result<PathBuf> {
+    let file_name = database_path
+        .file_name()
+        .ok_or_else(|| anyhow!("{}: not a file", database_path.display()))?
+        .to_string_lossy();
+    let backup_path = database_path.with_file_name(format!(
+        "{}.{}.bak",
+        file_name,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    copy(database_path, &backup_path)?;
+
+    Ok(backup_path)
+}
+
+/// Reads the sideloaded books of the device, as opposed to those bought from Kobo
+pub fn read_sideloaded_books(db: &Connection) -> Result<Vec<(String, EpubInfo)>> {
+    let mut statement = db.prepare(
+        "SELECT ContentID, Title, Attribution, ISBN FROM content
+        WHERE ContentType = ?1 AND ContentID LIKE 'file://%'
+        ORDER BY ContentID",
+    )?;
+    let books = statement
+        .query_map(params![BOOK_CONTENT_TYPE], |row| {
+            let content_id: String = row.get(0)?;
+            let title: Option<String> = row.get(1)?;
+            let authors: Option<String> = row.get(2)?;
+            let isbn: Option<String> = row.get(3)?;
+
+            Ok((
+                content_id.clone(),
+                EpubInfo {
+                    path: PathBuf::from(content_id),
+                    title: title.unwrap_or_default(),
+                    // Authors may be written family name first, e.g. `Sanderson, Brandon`,
+                    // so only `&` separates them
+                    authors: authors
+                        .unwrap_or_default()
+                        .split('&')
+                        .map(|author| author.trim().to_string())
+                        .filter(|author| !author.is_empty())
+                        .collect(),
+                    isbn: isbn.as_deref().and_then(Isbn::parse),
+                },
+            ))
+        })?
+        .collect::<Result<Vec<(String, EpubInfo)>, rusqlite::Error>>()?;
+
+    Ok(books)
+}
+
+pub fn update_content(db: &Connection, content_id: &str, update: &ContentUpdate) -> Result<()> {
+    db.execute(
+        "UPDATE content SET
+            Series = COALESCE(?1, Series),
+            SeriesNumber = COALESCE(?2, SeriesNumber),
+            Description = COALESCE(?3, Description),
+            Publisher = COALESCE(?4, Publisher),
+            Language = COALESCE(?5, Language)
+        WHERE ContentID = ?6 AND ContentType = ?7",
+        params![
+            update.series,
+            update.series_number,
+            update.description,
+            update.publisher,
+            update.language,
+            content_id,
+            BOOK_CONTENT_TYPE,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Matches the sideloaded books of a Kobo eReader to Kobo books and fills in their
+/// series, description, publisher and language, after backing up the database
+pub fn update_device(path: &Path, limit: usize, threshold: f64, config: &Config) -> Result<()> {
+    let database_path = find_database(path)?;
+    let backup_path = back_up_database(&database_path)?;
+    println!("Backed up the database to {}", backup_path.display());
+
+    // Each book is updated on its own, so no write lock is held during the lookups
+    let db = Connection::open(&database_path)?;
+    let books = read_sideloaded_books(&db)?;
+
+    let pb = ProgressBar::new(books.len() as u64);
+    for (content_id, book) in books {
+        match book.find_best_match(limit) {
+            Ok(Some((confidence, metadata))) if confidence >= threshold => {
+                update_content(
+                    &db,
+                    &content_id,
+                    &ContentUpdate::from_metadata(&metadata, config),
+                )?;
+            }
+            Ok(_) => pb.println(format!("No confident match for {}", book.title)),
+            Err(error) => pb.println(format!("Skipping {}: {}", book.title, error)),
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_database() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE content (
+                ContentID TEXT, ContentType TEXT, Title TEXT, Attribution TEXT, ISBN TEXT,
+                Series TEXT, SeriesNumber TEXT, Description TEXT, Publisher TEXT, Language TEXT
+            );
+            INSERT INTO content (ContentID, ContentType, Title, Attribution, ISBN, Publisher) VALUES
+                ('file:///mnt/onboard/Mistborn.epub', 6, 'Mistborn', 'Sanderson, Brandon', '9781429989817', 'Tor'),
+                ('file:///mnt/onboard/Mistborn.epub#(1)chapter1.xhtml', 9, 'Chapter 1', NULL, NULL, NULL),
+                ('tSfRgYbwtzGWxEne-NJKWw', 6, '迷霧之子首部曲：最後帝國', '布蘭登．山德森', NULL, NULL);",
+        )?;
+
+        Ok(db)
+    }
+
+    #[test]
+    fn read_sideloaded_book() -> Result<()> {
+        let books = read_sideloaded_books(&device_database()?)?;
+
+        assert_eq!(
+            books,
+            vec![(
+                "file:///mnt/onboard/Mistborn.epub".to_string(),
+                EpubInfo {
+                    path: PathBuf::from("file:///mnt/onboard/Mistborn.epub"),
+                    title: "Mistborn".to_string(),
+                    authors: vec!["Sanderson, Brandon".to_string()],
+                    isbn: Isbn::parse("9781429989817"),
+                }
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn update_sideloaded_book() -> Result<()> {
+        let db = device_database()?;
+        update_content(
+            &db,
+            "file:///mnt/onboard/Mistborn.epub",
+            &ContentUpdate {
+                series: Some("Mistborn".to_string()),
+                series_number: Some("1".to_string()),
+                language: Some("en".to_string()),
+                ..Default::default()
+            },
+        )?;
+        let content = db.query_row(
+            "SELECT Series, SeriesNumber, Publisher, Language FROM content WHERE ContentType = 6 AND Title = 'Mistborn'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+        assert_eq!(
+            content,
+            (
+                "Mistborn".to_string(),
+                "1".to_string(),
+                "Tor".to_string(),
+                "en".to_string()
+            )
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    book::Metadata,
+    config::Config,
+    filter::BookKind,
+    synopsis::SynopsisFormat,
+    xml::{escape_xml, XmlCollectionWriter},
+};
+
+const DUBLIN_CORE_FILE_PATH: &str = "./metadata.dc.xml";
+const DUBLIN_CORE_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<records xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n";
+const DUBLIN_CORE_FOOTER: &str = "</records>\n";
+
+/// Adds Dublin Core records to the collection in ./metadata.dc.xml
+pub fn dublin_core_writer() -> XmlCollectionWriter {
+    XmlCollectionWriter::new(
+        DUBLIN_CORE_FILE_PATH,
+        DUBLIN_CORE_HEADER.to_string(),
+        DUBLIN_CORE_FOOTER,
+        |metadata, config| Ok(to_dublin_core(metadata, config)),
+    )
+}
+
+fn to_dublin_core(metadata: &Metadata, config: &Config) -> String {
+    let mut elements = vec![("title", metadata.title().to_string())];
+    if let Some(subtitle) = metadata.subtitle() {
+        elements[0].1 = format!("{}: {}", metadata.title(), subtitle);
+    }
+    for author in metadata.authors() {
+        elements.push(("creator", author.to_string()));
+    }
+    for narrator in metadata.narrators() {
+        elements.push(("contributor", narrator.to_string()));
+    }
+    if !metadata.publisher().is_empty() {
+        elements.push(("publisher", metadata.publisher().to_string()));
+    }
+    if let Some(release_date) = metadata.release_date() {
+        elements.push(("date", release_date.format("%Y-%m-%d").to_string()));
+    }
+    let description = metadata.synopsis(SynopsisFormat::Text);
+    if !description.is_empty() {
+        elements.push(("description", description));
+    }
+    for tag_path in metadata.get_tag_paths(config) {
+        elements.push(("subject", tag_path.join(" -- ")));
+    }
+    if !metadata.language_code().is_empty() {
+        elements.push(("language", metadata.language_code().to_string()));
+    }
+    let kind = match metadata.kind() {
+        BookKind::Ebook => "Text",
+        BookKind::Audiobook => "Sound",
+    };
+    elements.push(("type", kind.to_string()));
+    if let Some(isbn) = metadata.isbn() {
+        elements.push(("identifier", format!("urn:isbn:{}", isbn.isbn_13)));
+    }
+    elements.push(("identifier", metadata.url()));
+
+    let elements = elements
+        .iter()
+        .map(|(name, value)| format!("    <dc:{0}>{1}</dc:{0}>\n", name, escape_xml(value)))
+        .collect::<String>();
+
+    format!("  <record>\n{}  </record>\n", elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn dublin_core_record() -> Result<()> {
+        let record = to_dublin_core(&Metadata::sample(), &Config::default());
+        let collection = format!("{}{}{}", DUBLIN_CORE_HEADER, record, DUBLIN_CORE_FOOTER);
+        let collection = roxmltree::Document::parse(&collection)?;
+        let elements = collection
+            .descendants()
+            .filter(|node| node.is_element() && node.tag_name().name() != "description")
+            .filter_map(|node| Some((node.tag_name().name(), node.text()?.trim())))
+            .filter(|(_, text)| !text.is_empty())
+            .collect::<Vec<(&str, &str)>>();
+
+        assert_eq!(
+            elements,
+            vec![
+                ("title", "Mistborn: The Final Empire"),
+                ("creator", "Brandon Sanderson"),
+                ("publisher", "Tor Publishing Group"),
+                ("date", "2010-04-01"),
+                ("subject", "Fiction & Literature -- Fantasy"),
+                ("language", "en"),
+                ("type", "Text"),
+                ("identifier", "urn:isbn:9781429989817"),
+                ("identifier", "https://www.kobo.com/tw/zh/ebook/mistborn-3"),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use crate::{book::Metadata, isbn::Isbn, search::search_books};
+use anyhow::{anyhow, Result};
+use indicatif::ProgressBar;
+use roxmltree::Document;
+use std::{
+    fs::{read_dir, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+pub const REVIEW_FILE_PATH: &str = "./epub-matches.csv";
+const REVIEW_HEADERS: [&str; 9] = [
+    "Accept",
+    "Confidence",
+    "EPUB Path",
+    "EPUB Title",
+    "EPUB Author(s)",
+    "EPUB ISBN",
+    "Kobo ID",
+    "Kobo Title",
+    "Kobo Author(s)",
+];
+
+#[derive(Debug, PartialEq)]
+pub struct EpubInfo {
+    pub path: PathBuf,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub isbn: Option<Isbn>,
+}
+
+impl EpubInfo {
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut epub = ZipArchive::new(File::open(path)?)?;
+
+        let container = read_zip_entry(&mut epub, "META-INF/container.xml")?;
+        let opf_path = Document::parse(&container)?
+            .descendants()
+            .find(|node| node.has_tag_name("rootfile"))
+            .and_then(|rootfile| rootfile.attribute("full-path"))
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("{}: no OPF rootfile", path.display()))?;
+        let opf = read_zip_entry(&mut epub, &opf_path)?;
+
+        Self::from_opf(path, &opf)
+    }
+
+    fn from_opf(path: &Path, opf: &str) -> Result<Self> {
+        let opf = Document::parse(opf)?;
+        let dc_texts = |name: &str| {
+            opf.descendants()
+                .filter(|node| node.has_tag_name((DC_NAMESPACE, name)))
+                .filter_map(|node| node.text())
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<String>>()
+        };
+
+        let title = dc_texts("title").into_iter().next().unwrap_or_default();
+        let authors = dc_texts("creator");
+        let isbn = dc_texts("identifier").iter().find_map(|identifier| {
+            let identifier = identifier
+                .trim_start_matches("urn:isbn:")
+                .trim_start_matches("URN:ISBN:");
+            Isbn::parse(identifier)
+        });
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            title,
+            authors,
+            isbn,
+        })
+    }
+
+    /// Searches Kobo and returns the result most likely to be this EPUB with its confidence.
+    /// The title and authors are searched when the ISBN finds nothing, as EPUBs often carry
+    /// a print ISBN that Kobo doesn't know.
+    pub fn find_best_match(&self, limit: usize) -> Result<Option<(f64, Metadata)>> {
+        for query in self.search_queries() {
+            let best_match = search_books(&query, limit)?
+                .into_iter()
+                .map(|book| (self.match_score(&book), book))
+                .max_by(|(a, _), (b, _)| a.total_cmp(b));
+            if best_match.is_some() {
+                return Ok(best_match);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn search_queries(&self) -> Vec<String> {
+        let title_query = format!("{} {}", self.title, self.authors.join(" "));
+
+        self.isbn
+            .iter()
+            .map(|isbn| isbn.isbn_13.clone())
+            .chain([title_query.trim().to_string()])
+            .filter(|query| !query.is_empty())
+            .collect()
+    }
+
+    /// Confidence from 0 to 1 that a Kobo book is this EPUB
+    pub fn match_score(&self, book: &Metadata) -> f64 {
+        if self.isbn.as_ref().is_some_and(|isbn| book.has_isbn(isbn)) {
+            return 1.0;
+        }
+
+        let title_score = similarity(&self.title, book.title());
+        let author_score = self
+            .authors
+            .iter()
+            .flat_map(|author| {
+                book.authors()
+                    .into_iter()
+                    .map(move |book_author| similarity(author, book_author))
+            })
+            .fold(None, |best: Option<f64>, score| {
+                Some(best.map_or(score, |best| best.max(score)))
+            });
+
+        match author_score {
+            Some(author_score) => title_score * 0.7 + author_score * 0.3,
+            None => title_score,
+        }
+    }
+}
+
+fn read_zip_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<String> {
+    let mut content = String::new();
+    zip.by_name(name)?.read_to_string(&mut content)?;
+
+    Ok(content)
+}
+
+pub fn find_epubs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut epubs = Vec::<PathBuf>::new();
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            epubs.extend(find_epubs(&path)?);
+            continue;
+        }
+
+        let is_epub = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("epub"));
+        if is_epub {
+            epubs.push(path);
+        }
+    }
+    epubs.sort();
+
+    Ok(epubs)
+}
+
+/// Searches Kobo for every EPUB in a folder and writes the best matches to a
+/// review file, accepting those scoring at least `threshold`
+pub fn write_review_file(dir: &Path, limit: usize, threshold: f64) -> Result<()> {
+    let epubs = find_epubs(dir)?;
+    let mut review_wtr = csv::Writer::from_path(REVIEW_FILE_PATH)?;
+    review_wtr.write_record(REVIEW_HEADERS)?;
+
+    let pb = ProgressBar::new(epubs.len() as u64);
+    for epub_path in epubs {
+        let epub = match EpubInfo::read(&epub_path) {
+            Ok(epub) => epub,
+            Err(error) => {
+                pb.println(format!("Skipping {}: {}", epub_path.display(), error));
+                pb.inc(1);
+                continue;
+            }
+        };
+
+        let best_match = match epub.find_best_match(limit) {
+            Ok(best_match) => best_match,
+            Err(error) => {
+                pb.println(format!("Skipping {}: {}", epub_path.display(), error));
+                pb.inc(1);
+                continue;
+            }
+        };
+        let (confidence, id, title, authors) = match &best_match {
+            Some((confidence, book)) => (
+                *confidence,
+                book.id(),
+                book.title(),
+                book.authors().join(" & "),
+            ),
+            None => (0.0, "", "", String::new()),
+        };
+        let is_accepted = best_match.is_some() && confidence >= threshold;
+
+        review_wtr.write_record([
+            if is_accepted { "yes" } else { "no" },
+            &format!("{:.2}", confidence),
+            &epub.path.display().to_string(),
+            &epub.title,
+            &epub.authors.join(" & "),
+            &epub
+                .isbn
+                .as_ref()
+                .map(|isbn| isbn.isbn_13.clone())
+                .unwrap_or_default(),
+            id,
+            title,
+            &authors,
+        ])?;
+        review_wtr.flush()?;
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    Ok(())
+}
+
+/// Reads the Kobo IDs of the accepted matches back from a review file
+pub fn read_accepted_book_ids(review_file: &Path) -> Result<Vec<String>> {
+    let mut review_rdr = csv::Reader::from_path(review_file)?;
+    let headers = review_rdr.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| anyhow!("{}: no \"{}\" column", review_file.display(), name))
+    };
+    let accept_column = column("Accept")?;
+    let id_column = column("Kobo ID")?;
+
+    let mut book_ids = Vec::<String>::new();
+    for record in review_rdr.records() {
+        let record = record?;
+        let is_accepted = record.get(accept_column).is_some_and(|accept| {
+            ["yes", "y", "true", "x"].contains(&accept.trim().to_lowercase().as_str())
+        });
+        let book_id = record.get(id_column).unwrap_or_default().trim();
+        if is_accepted && !book_id.is_empty() {
+            book_ids.push(book_id.to_string());
+        }
+    }
+
+    Ok(book_ids)
+}
+
+/// Dice coefficient of the character bigrams of two normalized strings
+fn similarity(a: &str, b: &str) -> f64 {
+    let normalize = |text: &str| {
+        text.to_lowercase()
+            .chars()
+            .filter(|char| char.is_alphanumeric())
+            .collect::<Vec<char>>()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    if a.len() < 2 || b.len() < 2 {
+        return if !a.is_empty() && a == b { 1.0 } else { 0.0 };
+    }
+
+    let a_bigrams = a.windows(2).collect::<Vec<&[char]>>();
+    let mut b_bigrams = b.windows(2).collect::<Vec<&[char]>>();
+    let mut shared_bigrams = 0;
+    for bigram in &a_bigrams {
+        if let Some(index) = b_bigrams.iter().position(|b_bigram| b_bigram == bigram) {
+            b_bigrams.swap_remove(index);
+            shared_bigrams += 1;
+        }
+    }
+
+    2.0 * shared_bigrams as f64 / (a.len() + b.len() - 2) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_opf() -> Result<()> {
+        let opf = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+                <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                    <dc:identifier id="uid">urn:uuid:0b7c3d5e-1f2a-4b6c-8d9e-0f1a2b3c4d5e</dc:identifier>
+                    <dc:identifier>urn:isbn:9781429989817</dc:identifier>
+                    <dc:title>Mistborn</dc:title>
+                    <dc:creator>Brandon Sanderson</dc:creator>
+                </metadata>
+            </package>"#;
+        let epub = EpubInfo::from_opf(Path::new("mistborn.epub"), opf)?;
+
+        assert_eq!(
+            epub,
+            EpubInfo {
+                path: PathBuf::from("mistborn.epub"),
+                title: "Mistborn".to_string(),
+                authors: vec!["Brandon Sanderson".to_string()],
+                isbn: Isbn::parse("9781429989817"),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn search_queries() {
+        let epub = EpubInfo {
+            path: PathBuf::from("mistborn.epub"),
+            title: "Mistborn".to_string(),
+            authors: vec!["Brandon Sanderson".to_string()],
+            isbn: Isbn::parse("9780765311788"),
+        };
+
+        assert_eq!(
+            epub.search_queries(),
+            vec![
+                "9780765311788".to_string(),
+                "Mistborn Brandon Sanderson".to_string()
+            ]
+        );
+        assert!(EpubInfo {
+            path: PathBuf::from("untitled.epub"),
+            title: String::new(),
+            authors: Vec::new(),
+            isbn: None,
+        }
+        .search_queries()
+        .is_empty())
+    }
+
+    #[test]
+    fn compare_titles() {
+        assert_eq!(similarity("Mistborn", "MISTBORN"), 1.0);
+        assert!(similarity("迷霧之子首部曲：最後帝國", "迷霧之子首部曲") > 0.7);
+        assert!(similarity("The Final Empire", "Let It Snow") < 0.3)
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::NaiveDate;
+use clap::{Args, ValueEnum};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default, ValueEnum)]
+pub enum BookKind {
+    #[default]
+    Ebook,
+    Audiobook,
+}
+
+/// Audiobook IDs carry this prefix so they can go through the same pipeline as
+/// ebook IDs, e.g. `audiobook/mistborn-1`
+const AUDIOBOOK_ID_PREFIX: &str = "audiobook/";
+
+impl BookKind {
+    pub fn path_segment(self) -> &'static str {
+        match self {
+            BookKind::Ebook => "/ebook/",
+            BookKind::Audiobook => "/audiobook/",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BookKind::Ebook => "ebook",
+            BookKind::Audiobook => "audiobook",
+        }
+    }
+
+    pub fn to_book_id(self, id: &str) -> String {
+        match self {
+            BookKind::Ebook => id.to_string(),
+            BookKind::Audiobook => format!("{}{}", AUDIOBOOK_ID_PREFIX, id),
+        }
+    }
+
+    /// Splits a book ID into its kind and its Kobo ID
+    pub fn split_book_id(book_id: &str) -> (BookKind, &str) {
+        match book_id.strip_prefix(AUDIOBOOK_ID_PREFIX) {
+            Some(id) => (BookKind::Audiobook, id),
+            None => (BookKind::Ebook, book_id),
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct BookFilter {
+    /// Only keep books in this language, as an ISO 639-1 code or BCP 47 tag
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Only keep books released on or after this date (yyyy-mm-dd)
+    #[arg(long)]
+    pub released_after: Option<NaiveDate>,
+
+    /// Only keep books released on or before this date (yyyy-mm-dd)
+    #[arg(long)]
+    pub released_before: Option<NaiveDate>,
+}
+
+impl BookFilter {
+    pub fn matches(
+        &self,
+        language_code: &str,
+        language_tag: &str,
+        release_date: Option<NaiveDate>,
+    ) -> bool {
+        let is_language_matched = self.language.as_ref().is_none_or(|language| {
+            language.eq_ignore_ascii_case(language_code)
+                || language.eq_ignore_ascii_case(language_tag)
+        });
+        let is_date_matched = match (self.released_after, self.released_before) {
+            (None, None) => true,
+            (after, before) => release_date.is_some_and(|date| {
+                after.is_none_or(|after| date >= after)
+                    && before.is_none_or(|before| date <= before)
+            }),
+        };
+
+        is_language_matched && is_date_matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_by_language() {
+        let filter = BookFilter {
+            language: Some("zh-Hant".to_string()),
+            ..Default::default()
+        };
+
+        assert!(filter.matches("zh", "zh-Hant", None));
+        assert!(!filter.matches("zh", "zh-Hans", None))
+    }
+
+    #[test]
+    fn filter_by_release_date() {
+        let filter = BookFilter {
+            released_after: NaiveDate::from_ymd_opt(2010, 1, 1),
+            released_before: NaiveDate::from_ymd_opt(2010, 12, 31),
+            ..Default::default()
+        };
+
+        assert!(filter.matches("en", "en", NaiveDate::from_ymd_opt(2010, 3, 30)));
+        assert!(!filter.matches("en", "en", NaiveDate::from_ymd_opt(2022, 5, 27)));
+        assert!(!filter.matches("en", "en", None))
+    }
+}
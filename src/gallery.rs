@@ -0,0 +1,409 @@
+use crate::{
+    column::Column,
+    config::Config,
+    csv_file::UTF8_BOM,
+    synopsis::{format_synopsis, SynopsisFormat},
+    xml::escape_xml,
+};
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    fs::{copy, create_dir_all, read, write},
+    path::Path,
+};
+
+pub const GALLERY_DIR: &str = "./gallery";
+const STYLE: &str = "body { font-family: system-ui, sans-serif; margin: 0 auto; max-width: 72rem; padding: 1rem; }
+header { display: flex; flex-wrap: wrap; gap: 0.5rem; margin-bottom: 1rem; }
+input, select { font: inherit; padding: 0.25rem 0.5rem; }
+.grid { display: grid; gap: 1rem; grid-template-columns: repeat(auto-fill, minmax(10rem, 1fr)); list-style: none; padding: 0; }
+.grid a { color: inherit; text-decoration: none; }
+.grid img, .book img { aspect-ratio: 3 / 4; background: #eee; object-fit: cover; width: 100%; }
+.grid p { margin: 0.25rem 0; }
+.book { display: grid; gap: 2rem; grid-template-columns: 16rem 1fr; }
+.muted { color: #666; }
+.current { font-weight: bold; }
+";
+const SEARCH_SCRIPT: &str = "const search = document.getElementById('search');
+const tag = document.getElementById('tag');
+function filter() {
+  const words = search.value.toLowerCase().split(/\\s+/).filter(Boolean);
+  for (const book of document.querySelectorAll('.grid li')) {
+    const text = book.dataset.search;
+    const tags = book.dataset.tags.split('\\n');
+    book.hidden = !words.every(word => text.includes(word)) || (tag.value && !tags.includes(tag.value));
+  }
+}
+search.addEventListener('input', filter);
+tag.addEventListener('change', filter);
+";
+
+/// A book as read back from the CSV file
+#[derive(Debug, Default, PartialEq)]
+struct GalleryBook {
+    id: String,
+    title: String,
+    subtitle: String,
+    authors: Vec<String>,
+    series: String,
+    series_index: Option<f64>,
+    cover: String,
+    synopsis: String,
+    tags: Vec<String>,
+    publisher: String,
+    release_date: String,
+}
+
+impl GalleryBook {
+    fn page_name(&self) -> String {
+        let name = self
+            .id
+            .chars()
+            .map(|char| {
+                if char.is_alphanumeric() || char == '-' || char == '_' {
+                    char
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>();
+
+        format!("{}.html", name)
+    }
+
+    fn cover_name(&self) -> Option<String> {
+        Path::new(&self.cover)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+
+    fn byline(&self) -> String {
+        self.authors.join(", ")
+    }
+
+    fn series_label(&self) -> Option<String> {
+        match (self.series.is_empty(), self.series_index) {
+            (true, _) => None,
+            (false, Some(index)) => Some(format!("{} #{}", self.series, index)),
+            (false, None) => Some(self.series.clone()),
+        }
+    }
+}
+
+/// Builds a static site from the CSV file and its covers: an index with a searchable grid
+/// of covers, and a page per book
+pub fn build_gallery(csv_path: &Path, out_dir: &Path, config: &Config) -> Result<usize> {
+    let books = read_gallery_books(&read(csv_path)?, config)?;
+    let csv_dir = csv_path.parent().unwrap_or(Path::new("."));
+
+    create_dir_all(out_dir.join("books"))?;
+    create_dir_all(out_dir.join("covers"))?;
+    write(out_dir.join("style.css"), STYLE)?;
+    write(out_dir.join("search.js"), SEARCH_SCRIPT)?;
+    for book in &books {
+        if let Some(cover_name) = book.cover_name() {
+            let cover_path = csv_dir.join(&book.cover);
+            if cover_path.exists() {
+                copy(&cover_path, out_dir.join("covers").join(cover_name))?;
+            }
+        }
+    }
+
+    write(out_dir.join("index.html"), index_page(&books))?;
+    let series = series_books(&books);
+    for book in &books {
+        write(
+            out_dir.join("books").join(book.page_name()),
+            book_page(book, series.get(book.series.as_str()), config),
+        )?;
+    }
+
+    Ok(books.len())
+}
+
+/// Reads the books of a CSV file written with the same column options as `config`
+fn read_gallery_books(content: &[u8], config: &Config) -> Result<Vec<GalleryBook>> {
+    let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let mut csv_rdr = csv::ReaderBuilder::new()
+        .delimiter(config.csv_delimiter)
+        .from_reader(content);
+    let headers = csv_rdr.headers()?.clone();
+    let columns = config
+        .csv_columns()
+        .into_iter()
+        .filter_map(|(column, header)| {
+            let position = headers.iter().position(|existing| existing == header)?;
+            Some((column, position))
+        })
+        .collect::<HashMap<Column, usize>>();
+    if !columns.contains_key(&Column::Id) || !columns.contains_key(&Column::Title) {
+        anyhow::bail!("The CSV file has no ID or title column under the headers this run expects; pass the column options it was written with");
+    }
+
+    let split = |value: &str, separator: &str| {
+        value
+            .split(separator)
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<String>>()
+    };
+    let mut books = Vec::<GalleryBook>::new();
+    for record in csv_rdr.records() {
+        let record = record?;
+        let value = |column| {
+            columns
+                .get(&column)
+                .and_then(|position| record.get(*position))
+                .unwrap_or_default()
+                .to_string()
+        };
+        books.push(GalleryBook {
+            id: value(Column::Id),
+            title: value(Column::Title),
+            subtitle: value(Column::Subtitle),
+            authors: split(&value(Column::Authors), &config.list_separator),
+            series: value(Column::Series),
+            series_index: value(Column::SeriesIndex).parse().ok(),
+            cover: value(Column::Cover),
+            synopsis: value(Column::Synopsis),
+            tags: split(&value(Column::Tags), &config.tag_separator),
+            publisher: value(Column::Publisher),
+            release_date: value(Column::ReleaseDate),
+        });
+    }
+
+    Ok(books)
+}
+
+/// The books of each series, in series order
+fn series_books(books: &[GalleryBook]) -> HashMap<&str, Vec<&GalleryBook>> {
+    let mut series = HashMap::<&str, Vec<&GalleryBook>>::new();
+    for book in books.iter().filter(|book| !book.series.is_empty()) {
+        series.entry(&book.series).or_default().push(book);
+    }
+    for books in series.values_mut() {
+        books.sort_by(|a, b| {
+            a.series_index
+                .unwrap_or(f64::MAX)
+                .total_cmp(&b.series_index.unwrap_or(f64::MAX))
+        });
+    }
+
+    series
+}
+
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<link rel=\"stylesheet\" href=\"{}style.css\">\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_xml(title),
+        root,
+        body
+    )
+}
+
+fn cover_img(book: &GalleryBook, root: &str) -> String {
+    match book.cover_name() {
+        Some(cover_name) => format!(
+            "<img src=\"{}covers/{}\" alt=\"{}\" loading=\"lazy\">",
+            root,
+            escape_xml(&cover_name),
+            escape_xml(&book.title)
+        ),
+        None => String::new(),
+    }
+}
+
+fn index_page(books: &[GalleryBook]) -> String {
+    let mut tags = books
+        .iter()
+        .flat_map(|book| book.tags.iter())
+        .collect::<Vec<&String>>();
+    tags.sort();
+    tags.dedup();
+    let tag_options = tags
+        .iter()
+        .map(|tag| format!("<option>{}</option>", escape_xml(tag)))
+        .collect::<String>();
+
+    let cards = books
+        .iter()
+        .map(|book| {
+            let search = [
+                book.title.as_str(),
+                book.subtitle.as_str(),
+                &book.byline(),
+                &book.series,
+                &book.publisher,
+                &book.tags.join(" "),
+            ]
+            .join(" ")
+            .to_lowercase();
+            let series = book
+                .series_label()
+                .map(|series| format!("<p class=\"muted\">{}</p>", escape_xml(&series)))
+                .unwrap_or_default();
+            format!(
+                "<li data-search=\"{}\" data-tags=\"{}\"><a href=\"books/{}\">{}<p>{}</p><p class=\"muted\">{}</p>{}</a></li>\n",
+                escape_xml(&search),
+                escape_xml(&book.tags.join("\n")),
+                book.page_name(),
+                cover_img(book, ""),
+                escape_xml(&book.title),
+                escape_xml(&book.byline()),
+                series
+            )
+        })
+        .collect::<String>();
+
+    page(
+        "Library",
+        "",
+        &format!(
+            "<header>\n<input id=\"search\" type=\"search\" placeholder=\"Search titles, authors, series…\">\n<select id=\"tag\"><option value=\"\">All tags</option>{}</select>\n<span class=\"muted\">{} books</span>\n</header>\n<ul class=\"grid\">\n{}</ul>\n<script src=\"search.js\"></script>\n",
+            tag_options,
+            books.len(),
+            cards
+        ),
+    )
+}
+
+fn book_page(book: &GalleryBook, series: Option<&Vec<&GalleryBook>>, config: &Config) -> String {
+    let mut details = Vec::<String>::new();
+    if !book.subtitle.is_empty() {
+        details.push(format!("<p>{}</p>", escape_xml(&book.subtitle)));
+    }
+    details.push(format!("<p>{}</p>", escape_xml(&book.byline())));
+    let published = [book.publisher.as_str(), book.release_date.as_str()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    if !published.is_empty() {
+        details.push(format!("<p class=\"muted\">{}</p>", escape_xml(&published)));
+    }
+    if !book.tags.is_empty() {
+        let tags = book
+            .tags
+            .iter()
+            .map(|tag| escape_xml(&tag.replace('.', " › ")))
+            .collect::<Vec<String>>()
+            .join(" · ");
+        details.push(format!("<p class=\"muted\">{}</p>", tags));
+    }
+    details.push(synopsis_html(&book.synopsis, config.synopsis_format));
+
+    if let Some(series) = series {
+        let position = series
+            .iter()
+            .position(|series_book| series_book.id == book.id);
+        let link = |series_book: &GalleryBook, label: &str| {
+            format!(
+                "<a href=\"{}\">{}</a>",
+                series_book.page_name(),
+                escape_xml(label)
+            )
+        };
+        let mut navigation = Vec::<String>::new();
+        if let Some(previous) = position.and_then(|position| position.checked_sub(1)) {
+            navigation.push(link(
+                series[previous],
+                &format!("← {}", series[previous].title),
+            ));
+        }
+        if let Some(next) = position.and_then(|position| series.get(position + 1)) {
+            navigation.push(link(next, &format!("{} →", next.title)));
+        }
+        let books = series
+            .iter()
+            .map(|series_book| match series_book.id == book.id {
+                true => format!(
+                    "<li class=\"current\">{}</li>",
+                    escape_xml(&series_book.title)
+                ),
+                false => format!("<li>{}</li>", link(series_book, &series_book.title)),
+            })
+            .collect::<String>();
+        details.push(format!(
+            "<nav>\n<h2>{}</h2>\n<p>{}</p>\n<ol>{}</ol>\n</nav>",
+            escape_xml(&book.series),
+            navigation.join(" · "),
+            books
+        ));
+    }
+
+    page(
+        &book.title,
+        "../",
+        &format!(
+            "<p><a href=\"../index.html\">← Library</a></p>\n<article class=\"book\">\n<div>{}</div>\n<div>\n<h1>{}</h1>\n{}\n</div>\n</article>\n",
+            cover_img(book, "../"),
+            escape_xml(&book.title),
+            details.join("\n")
+        ),
+    )
+}
+
+/// Synopsis as safe HTML, whatever format the CSV file holds it in. The CSV file may have
+/// been edited since it was written, so sanitized HTML is sanitized again.
+fn synopsis_html(synopsis: &str, format: SynopsisFormat) -> String {
+    match format {
+        SynopsisFormat::Html | SynopsisFormat::SanitizedHtml => {
+            format_synopsis(synopsis, SynopsisFormat::SanitizedHtml)
+        }
+        SynopsisFormat::Markdown | SynopsisFormat::Text => synopsis
+            .split("\n\n")
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .map(|paragraph| format!("<p>{}</p>", escape_xml(paragraph.trim())))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_demo_csv() -> Result<()> {
+        let books = read_gallery_books(include_bytes!("../demo/metadata.csv"), &Config::default())?;
+        let series = series_books(&books);
+
+        assert_eq!(books.len(), 3);
+        assert_eq!(books[0].authors, vec!["Brandon Sanderson"]);
+        assert_eq!(books[0].cover_name(), Some("1.jpg".to_string()));
+        assert_eq!(
+            series["MISTBORN"]
+                .iter()
+                .map(|book| book.id.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["the-hero-of-ages", "the-well-of-ascension", "mistborn-3"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn series_navigation() -> Result<()> {
+        let books = read_gallery_books(include_bytes!("../demo/metadata.csv"), &Config::default())?;
+        let series = series_books(&books);
+        let page = book_page(
+            &books[1],
+            series.get(books[1].series.as_str()),
+            &Config::default(),
+        );
+
+        assert!(page.contains("<a href=\"the-hero-of-ages.html\">← The Hero of Ages</a>"));
+        assert!(page.contains("<a href=\"mistborn-3.html\">Mistborn →</a>"));
+        assert!(page.contains("<li class=\"current\">The Well of Ascension</li>"));
+        Ok(())
+    }
+
+    #[test]
+    fn sanitize_edited_synopsis() {
+        let synopsis = synopsis_html(
+            "<p>synopsis</p><script>alert(1)</script><img src=x onerror=alert(1)>",
+            SynopsisFormat::SanitizedHtml,
+        );
+
+        assert!(!synopsis.contains("<script") && !synopsis.contains("onerror"))
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{book::Id, crawler::to_absolute_url, isbn::Isbn, search::find_book_id_by_isbn};
+use anyhow::Result;
+use indicatif::ProgressBar;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use std::{fs::read_to_string, path::Path};
+
+/// The values of a wishlist link or an export row, each with the column header or JSON key
+/// it was found under, or an empty label
+type Record = Vec<(String, String)>;
+
+/// Collects the IDs of the books in a saved Kobo wishlist page or a Kobo data-export
+/// file. Books listed only by ISBN are looked up on Kobo.
+pub fn read_book_ids(path: &Path) -> Result<Vec<String>> {
+    let content = read_to_string(path)?;
+    let is_html = path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+    }) || content.trim_start().starts_with('<');
+    let records = match is_html {
+        true => get_link_hrefs(&content)
+            .into_iter()
+            .map(|href| vec![(String::new(), href)])
+            .collect(),
+        false => split_export(&content)?,
+    };
+    let (mut book_ids, isbns) = extract_book_ids(&records);
+
+    let pb = ProgressBar::new(isbns.len() as u64);
+    for isbn in isbns {
+        match find_book_id_by_isbn(&isbn) {
+            Ok(Some(book_id)) if !book_ids.contains(&book_id) => book_ids.push(book_id),
+            Ok(Some(_)) => {}
+            Ok(None) => pb.println(format!(
+                "Warning: no Kobo book found for ISBN {}",
+                isbn.isbn_13
+            )),
+            Err(error) => pb.println(format!(
+                "Warning: couldn't look up ISBN {}: {}",
+                isbn.isbn_13, error
+            )),
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    Ok(book_ids)
+}
+
+fn get_link_hrefs(html: &str) -> Vec<String> {
+    let link_selector = Selector::parse("a[href]").expect("Invalid selector");
+    let link_hrefs = Html::parse_document(html)
+        .select(&link_selector)
+        .filter_map(|a| a.value().attr("href"))
+        .map(str::to_string)
+        .collect();
+
+    link_hrefs
+}
+
+/// Splits a JSON, CSV or plain text export into records. CSV values are labelled with the
+/// header of their column, and plain text is read as a CSV file of one column.
+fn split_export(export: &str) -> Result<Vec<Record>> {
+    if let Ok(json) = serde_json::from_str::<Value>(export) {
+        let mut records = Vec::<Record>::new();
+        collect_json_records(&json, &mut records);
+        return Ok(records);
+    }
+
+    let header_line = export.lines().next().unwrap_or_default();
+    let delimiter = [b',', b';', b'\t']
+        .into_iter()
+        .max_by_key(|delimiter| header_line.bytes().filter(|byte| byte == delimiter).count())
+        .unwrap_or(b',');
+    let mut csv_rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(export.as_bytes());
+    let rows = csv_rdr
+        .records()
+        .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+    // The first row is read as data too, as plain text has no header
+    let header = rows.first().cloned().unwrap_or_default();
+    let records = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    let label = header.get(index).unwrap_or_default();
+                    (label.trim().to_string(), value.trim().to_string())
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(records)
+}
+
+fn collect_json_records(json: &Value, records: &mut Vec<Record>) {
+    match json {
+        Value::Array(items) => {
+            for item in items {
+                collect_json_records(item, records);
+            }
+        }
+        Value::Object(object) => {
+            let mut record = Record::new();
+            for (key, value) in object {
+                match value {
+                    Value::String(text) => record.push((key.clone(), text.clone())),
+                    Value::Number(number) => record.push((key.clone(), number.to_string())),
+                    _ => collect_json_records(value, records),
+                }
+            }
+            records.push(record);
+        }
+        Value::String(text) => records.push(vec![(String::new(), text.clone())]),
+        _ => {}
+    }
+}
+
+/// Sorts records into Kobo book IDs, from book URLs, and ISBNs, both deduplicated. ISBNs
+/// are only taken from ISBN columns or keys, or values labelled like `ISBN: …`, and only
+/// from records without a book URL.
+fn extract_book_ids(records: &[Record]) -> (Vec<String>, Vec<Isbn>) {
+    let mut book_ids = Vec::<String>::new();
+    let mut isbns = Vec::<Isbn>::new();
+    for record in records {
+        let record_book_ids = record
+            .iter()
+            .flat_map(|(_, value)| value.split_whitespace())
+            .filter_map(|token| Id::from(to_absolute_url(token).as_str()))
+            .collect::<Vec<String>>();
+        if !record_book_ids.is_empty() {
+            for book_id in record_book_ids {
+                if !book_ids.contains(&book_id) {
+                    book_ids.push(book_id);
+                }
+            }
+            continue;
+        }
+
+        for (label, value) in record {
+            let isbn = match strip_isbn_label(value) {
+                Some(isbn) => Isbn::parse(isbn),
+                None if label.to_lowercase().contains("isbn") => Isbn::parse(value),
+                None => None,
+            };
+            if let Some(isbn) = isbn.filter(|isbn| !isbns.contains(isbn)) {
+                isbns.push(isbn);
+            }
+        }
+    }
+
+    (book_ids, isbns)
+}
+
+/// The ISBN in a value like `ISBN-13: 978-…` or `urn:isbn:978…`
+fn strip_isbn_label(value: &str) -> Option<&str> {
+    let lowercase = value.trim().to_ascii_lowercase();
+    let rest = lowercase
+        .strip_prefix("urn:isbn:")
+        .or_else(|| lowercase.strip_prefix("isbn"))?;
+    let rest = rest
+        .strip_prefix("-13")
+        .or_else(|| rest.strip_prefix("-10"))
+        .unwrap_or(rest);
+
+    value.trim().get(lowercase.len() - rest.len()..)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wishlist_book_ids() {
+        let wishlist = r#"<ul class="wishlist">
+            <li><h2 class="title"><a href="/tw/zh/ebook/mistborn-3?utm_source=wishlist">Mistborn</a></h2></li>
+            <li><a href="https://www.kobo.com/tw/zh/audiobook/mistborn-1">Mistborn</a></li>
+            <li><a href="/tw/zh/ebook/mistborn-3#reviews">Reviews</a></li>
+            <li><a href="/tw/zh/account/wishlist?pageNumber=2">Next</a></li>
+        </ul>"#;
+        let records = get_link_hrefs(wishlist)
+            .into_iter()
+            .map(|href| vec![(String::new(), href)])
+            .collect::<Vec<Record>>();
+        let (book_ids, isbns) = extract_book_ids(&records);
+
+        assert_eq!(
+            book_ids,
+            vec!["mistborn-3".to_string(), "audiobook/mistborn-1".to_string()]
+        );
+        assert!(isbns.is_empty())
+    }
+
+    fn isbn_13s(isbns: &[Isbn]) -> Vec<&str> {
+        isbns.iter().map(|isbn| isbn.isbn_13.as_str()).collect()
+    }
+
+    #[test]
+    fn export_book_ids() -> Result<()> {
+        let export = "Title,ISBN,Order,Product URL\n\
+            Mistborn,9781429989817,0306406152,https://www.kobo.com/us/en/ebook/mistborn-3\n\
+            不便利的便利店,9786269593859,0306406152,\n";
+        let (book_ids, isbns) = extract_book_ids(&split_export(export)?);
+
+        assert_eq!(book_ids, vec!["mistborn-3".to_string()]);
+        assert_eq!(isbn_13s(&isbns), vec!["9786269593859"]);
+        Ok(())
+    }
+
+    #[test]
+    fn json_and_text_export_book_ids() -> Result<()> {
+        let json = r#"[{"title": "Mistborn", "isbn": "9781429989817", "order": "0306406152"}]"#;
+        let text = "Mistborn, ISBN-13: 978-1-4299-8981-7\nOrder 0306406152\n";
+
+        assert_eq!(
+            isbn_13s(&extract_book_ids(&split_export(json)?).1),
+            vec!["9781429989817"]
+        );
+        assert_eq!(
+            isbn_13s(&extract_book_ids(&split_export(text)?).1),
+            vec!["9781429989817"]
+        );
+        Ok(())
+    }
+}
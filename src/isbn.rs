@@ -0,0 +1,217 @@
+use crate::date::parse_release_date;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Isbn {
+    pub isbn_13: String,
+    pub isbn_10: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Identifier {
+    Isbn(Isbn),
+    /// Kobo's own product identifier, used for books published without an ISBN
+    ProductId(String),
+    /// Whatever was found in place of the ISBN, kept for the warning
+    Invalid(String),
+}
+
+impl Isbn {
+    pub fn parse(text: &str) -> Option<Self> {
+        let isbn = normalize(text);
+        if !isbn.is_ascii() {
+            return None;
+        }
+
+        match isbn.len() {
+            13 if is_valid_isbn_13(&isbn) => Some(Self {
+                isbn_10: isbn_13_to_10(&isbn),
+                isbn_13: isbn,
+            }),
+            10 if is_valid_isbn_10(&isbn) => Some(Self {
+                isbn_13: isbn_10_to_13(&isbn),
+                isbn_10: Some(isbn),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Identifier {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        if let Some(isbn) = Isbn::parse(text) {
+            return Some(Self::Isbn(isbn));
+        }
+
+        let id = normalize(text);
+        let looks_like_isbn = id.starts_with("978") || id.starts_with("979") || id.len() == 10;
+        let is_product_id = id.len() >= 8
+            && id.chars().all(|char| char.is_ascii_alphanumeric())
+            && !looks_like_isbn
+            && parse_release_date(text).is_none();
+        match is_product_id {
+            true => Some(Self::ProductId(id)),
+            false => Some(Self::Invalid(text.to_string())),
+        }
+    }
+}
+
+/// Uppercases the text and drops separators and a leading `ISBN`, `ISBN-10` or `ISBN-13`
+/// label, in any case
+fn normalize(text: &str) -> String {
+    let text = text.trim().to_uppercase();
+    let text = match text.strip_prefix("ISBN") {
+        Some(rest) => rest
+            .strip_prefix("-13")
+            .or_else(|| rest.strip_prefix("-10"))
+            .unwrap_or(rest),
+        None => &text,
+    };
+
+    text.replace(['-', ' ', ':'], "")
+}
+
+fn is_valid_isbn_13(isbn: &str) -> bool {
+    let is_bookland = isbn.starts_with("978") || isbn.starts_with("979");
+    let Some(digits) = to_digits(isbn) else {
+        return false;
+    };
+
+    is_bookland && checksum_13(&digits[..12]) == digits[12]
+}
+
+fn is_valid_isbn_10(isbn: &str) -> bool {
+    let (body, check) = isbn.split_at(9);
+    let Some(digits) = to_digits(body) else {
+        return false;
+    };
+
+    checksum_10(&digits) == check
+}
+
+fn isbn_10_to_13(isbn_10: &str) -> String {
+    let body = format!("978{}", &isbn_10[..9]);
+    let digits = to_digits(&body).unwrap_or_default();
+
+    format!("{}{}", body, checksum_13(&digits))
+}
+
+fn isbn_13_to_10(isbn_13: &str) -> Option<String> {
+    let body = isbn_13.strip_prefix("978")?.get(..9)?;
+    let digits = to_digits(body)?;
+
+    Some(format!("{}{}", body, checksum_10(&digits)))
+}
+
+fn to_digits(text: &str) -> Option<Vec<u32>> {
+    text.chars().map(|char| char.to_digit(10)).collect()
+}
+
+fn checksum_13(digits: &[u32]) -> u32 {
+    let sum = digits
+        .iter()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { *digit } else { digit * 3 })
+        .sum::<u32>();
+
+    (10 - sum % 10) % 10
+}
+
+fn checksum_10(digits: &[u32]) -> String {
+    let sum = digits
+        .iter()
+        .enumerate()
+        .map(|(index, digit)| (10 - index as u32) * digit)
+        .sum::<u32>();
+
+    match (11 - sum % 11) % 11 {
+        10 => "X".to_string(),
+        check => check.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_isbn_13() {
+        let isbn = Isbn::parse("978-1-4299-8981-7");
+
+        assert_eq!(
+            isbn,
+            Some(Isbn {
+                isbn_13: "9781429989817".to_string(),
+                isbn_10: Some("1429989815".to_string())
+            })
+        )
+    }
+
+    #[test]
+    fn parse_isbn_10() {
+        let isbn = Isbn::parse("0-8044-2957-X");
+
+        assert_eq!(
+            isbn,
+            Some(Isbn {
+                isbn_13: "9780804429573".to_string(),
+                isbn_10: Some("080442957X".to_string())
+            })
+        )
+    }
+
+    #[test]
+    fn parse_labelled_isbns() {
+        let isbns = [
+            "isbn 9781429989817",
+            "ISBN-13: 978-1-4299-8981-7",
+            "ISBN-10: 1429989815",
+        ]
+        .map(|text| Isbn::parse(text).map(|isbn| isbn.isbn_13));
+
+        assert_eq!(
+            isbns,
+            ["9781429989817"; 3].map(|isbn| Some(isbn.to_string()))
+        )
+    }
+
+    #[test]
+    fn parse_979_isbn() {
+        let isbn = Isbn::parse("9791032305690");
+
+        assert_eq!(
+            isbn,
+            Some(Isbn {
+                isbn_13: "9791032305690".to_string(),
+                isbn_10: None
+            })
+        )
+    }
+
+    #[test]
+    fn parse_kobo_product_id() {
+        let identifier = Identifier::parse("1230004567890");
+
+        assert_eq!(
+            identifier,
+            Some(Identifier::ProductId("1230004567890".to_string()))
+        )
+    }
+
+    #[test]
+    fn parse_invalid_identifiers() {
+        let identifiers = ["9781429989818", "2022年5月27日"].map(Identifier::parse);
+
+        assert_eq!(
+            identifiers,
+            [
+                Some(Identifier::Invalid("9781429989818".to_string())),
+                Some(Identifier::Invalid("2022年5月27日".to_string()))
+            ]
+        )
+    }
+}
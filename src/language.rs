@@ -0,0 +1,609 @@
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Language {
+    /// ISO 639-1 code, e.g. `zh`
+    pub code: String,
+    /// BCP 47 tag, e.g. `zh-Hant`
+    pub tag: String,
+}
+
+struct LanguageNames {
+    code: &'static str,
+    tag: &'static str,
+    names: &'static [&'static str],
+}
+
+/// Language names as displayed on the Kobo stores, keyed by their codes. Names
+/// carrying a script come before the bare language, so they win the lookup.
+const LANGUAGE_NAMES: &[LanguageNames] = &[
+    LanguageNames {
+        code: "zh",
+        tag: "zh-Hant",
+        names: &[
+            "中文(繁體)",
+            "繁體中文",
+            "正體中文",
+            "Chinese (Traditional)",
+            "Traditional Chinese",
+            "中国語(繁体字)",
+            "chinois traditionnel",
+        ],
+    },
+    LanguageNames {
+        code: "zh",
+        tag: "zh-Hans",
+        names: &[
+            "中文(簡體)",
+            "中文(简体)",
+            "簡體中文",
+            "简体中文",
+            "Chinese (Simplified)",
+            "Simplified Chinese",
+            "中国語(簡体字)",
+            "chinois simplifié",
+        ],
+    },
+    LanguageNames {
+        code: "zh",
+        tag: "zh",
+        names: &[
+            "中文",
+            "Chinese",
+            "中国語",
+            "chinois",
+            "Chinesisch",
+            "chino",
+            "cinese",
+            "Chinees",
+            "chinês",
+            "중국어",
+        ],
+    },
+    LanguageNames {
+        code: "en",
+        tag: "en",
+        names: &[
+            "英文", "English", "英語", "anglais", "Englisch", "inglés", "inglese", "Engels",
+            "inglês", "영어",
+        ],
+    },
+    LanguageNames {
+        code: "ja",
+        tag: "ja",
+        names: &[
+            "日文",
+            "Japanese",
+            "日本語",
+            "japonais",
+            "Japanisch",
+            "japonés",
+            "giapponese",
+            "Japans",
+            "japonês",
+            "일본어",
+        ],
+    },
+    LanguageNames {
+        code: "ko",
+        tag: "ko",
+        names: &[
+            "韓文",
+            "Korean",
+            "韓国語",
+            "coréen",
+            "Koreanisch",
+            "coreano",
+            "Koreaans",
+            "한국어",
+        ],
+    },
+    LanguageNames {
+        code: "fr",
+        tag: "fr",
+        names: &[
+            "法文",
+            "French",
+            "フランス語",
+            "français",
+            "Französisch",
+            "francés",
+            "francese",
+            "Frans",
+            "francês",
+            "프랑스어",
+        ],
+    },
+    LanguageNames {
+        code: "de",
+        tag: "de",
+        names: &[
+            "德文",
+            "German",
+            "ドイツ語",
+            "allemand",
+            "Deutsch",
+            "alemán",
+            "tedesco",
+            "Duits",
+            "alemão",
+            "독일어",
+        ],
+    },
+    LanguageNames {
+        code: "es",
+        tag: "es",
+        names: &[
+            "西班牙文",
+            "Spanish",
+            "スペイン語",
+            "espagnol",
+            "Spanisch",
+            "español",
+            "spagnolo",
+            "Spaans",
+            "espanhol",
+            "스페인어",
+        ],
+    },
+    LanguageNames {
+        code: "it",
+        tag: "it",
+        names: &[
+            "義大利文",
+            "意大利文",
+            "Italian",
+            "イタリア語",
+            "italien",
+            "Italienisch",
+            "italiano",
+            "Italiaans",
+            "이탈리아어",
+        ],
+    },
+    LanguageNames {
+        code: "pt",
+        tag: "pt",
+        names: &[
+            "葡萄牙文",
+            "Portuguese",
+            "ポルトガル語",
+            "portugais",
+            "Portugiesisch",
+            "portugués",
+            "portoghese",
+            "Portugees",
+            "português",
+        ],
+    },
+    LanguageNames {
+        code: "nl",
+        tag: "nl",
+        names: &[
+            "荷蘭文",
+            "Dutch",
+            "オランダ語",
+            "néerlandais",
+            "Niederländisch",
+            "neerlandés",
+            "olandese",
+            "Nederlands",
+            "holandês",
+        ],
+    },
+    LanguageNames {
+        code: "ru",
+        tag: "ru",
+        names: &[
+            "俄文",
+            "Russian",
+            "ロシア語",
+            "russe",
+            "Russisch",
+            "ruso",
+            "russo",
+            "русский",
+        ],
+    },
+    LanguageNames {
+        code: "sv",
+        tag: "sv",
+        names: &[
+            "瑞典文",
+            "Swedish",
+            "スウェーデン語",
+            "suédois",
+            "Schwedisch",
+            "sueco",
+            "svedese",
+            "Zweeds",
+            "svenska",
+        ],
+    },
+    LanguageNames {
+        code: "da",
+        tag: "da",
+        names: &[
+            "丹麥文",
+            "Danish",
+            "デンマーク語",
+            "danois",
+            "Dänisch",
+            "danés",
+            "danese",
+            "Deens",
+            "dansk",
+        ],
+    },
+    LanguageNames {
+        code: "no",
+        tag: "no",
+        names: &[
+            "挪威文",
+            "Norwegian",
+            "ノルウェー語",
+            "norvégien",
+            "Norwegisch",
+            "noruego",
+            "norvegese",
+            "Noors",
+            "norsk",
+        ],
+    },
+    LanguageNames {
+        code: "fi",
+        tag: "fi",
+        names: &[
+            "芬蘭文",
+            "Finnish",
+            "フィンランド語",
+            "finnois",
+            "Finnisch",
+            "finés",
+            "finlandese",
+            "Fins",
+            "suomi",
+        ],
+    },
+    LanguageNames {
+        code: "pl",
+        tag: "pl",
+        names: &[
+            "波蘭文",
+            "Polish",
+            "ポーランド語",
+            "polonais",
+            "Polnisch",
+            "polaco",
+            "polacco",
+            "Pools",
+            "polski",
+        ],
+    },
+    LanguageNames {
+        code: "tr",
+        tag: "tr",
+        names: &[
+            "土耳其文",
+            "Turkish",
+            "トルコ語",
+            "turc",
+            "Türkisch",
+            "turco",
+            "Turks",
+            "Türkçe",
+        ],
+    },
+    LanguageNames {
+        code: "th",
+        tag: "th",
+        names: &[
+            "泰文",
+            "Thai",
+            "タイ語",
+            "thaï",
+            "Thailändisch",
+            "tailandés",
+            "tailandese",
+            "Thais",
+        ],
+    },
+    LanguageNames {
+        code: "vi",
+        tag: "vi",
+        names: &[
+            "越南文",
+            "Vietnamese",
+            "ベトナム語",
+            "vietnamien",
+            "Vietnamesisch",
+            "vietnamita",
+            "Vietnamees",
+        ],
+    },
+    LanguageNames {
+        code: "id",
+        tag: "id",
+        names: &[
+            "印尼文",
+            "Indonesian",
+            "インドネシア語",
+            "indonésien",
+            "Indonesisch",
+            "indonesio",
+            "indonesiano",
+        ],
+    },
+    LanguageNames {
+        code: "ms",
+        tag: "ms",
+        names: &[
+            "馬來文",
+            "Malay",
+            "マレー語",
+            "malais",
+            "Malaiisch",
+            "malayo",
+            "malese",
+            "Maleis",
+        ],
+    },
+    LanguageNames {
+        code: "ar",
+        tag: "ar",
+        names: &[
+            "阿拉伯文",
+            "Arabic",
+            "アラビア語",
+            "arabe",
+            "Arabisch",
+            "árabe",
+            "arabo",
+        ],
+    },
+    LanguageNames {
+        code: "he",
+        tag: "he",
+        names: &[
+            "希伯來文",
+            "Hebrew",
+            "ヘブライ語",
+            "hébreu",
+            "Hebräisch",
+            "hebreo",
+            "ebraico",
+            "Hebreeuws",
+        ],
+    },
+    LanguageNames {
+        code: "el",
+        tag: "el",
+        names: &[
+            "希臘文",
+            "Greek",
+            "ギリシャ語",
+            "grec",
+            "Griechisch",
+            "griego",
+            "greco",
+            "Grieks",
+        ],
+    },
+    LanguageNames {
+        code: "ca",
+        tag: "ca",
+        names: &[
+            "加泰隆尼亞文",
+            "Catalan",
+            "カタルーニャ語",
+            "catalan",
+            "Katalanisch",
+            "catalán",
+            "catalano",
+            "Catalaans",
+            "català",
+        ],
+    },
+    LanguageNames {
+        code: "la",
+        tag: "la",
+        names: &[
+            "拉丁文",
+            "Latin",
+            "ラテン語",
+            "Latein",
+            "latín",
+            "latino",
+            "Latijn",
+        ],
+    },
+];
+
+/// ISO 639-2 codes that may show up in `lang` attributes or JSON-LD. Languages with
+/// two codes list the terminology code first, then the bibliographic one.
+const ISO_639_2_CODES: &[(&str, &str)] = &[
+    ("zho", "zh"),
+    ("chi", "zh"),
+    ("eng", "en"),
+    ("jpn", "ja"),
+    ("kor", "ko"),
+    ("fra", "fr"),
+    ("fre", "fr"),
+    ("deu", "de"),
+    ("ger", "de"),
+    ("spa", "es"),
+    ("ita", "it"),
+    ("por", "pt"),
+    ("nld", "nl"),
+    ("dut", "nl"),
+    ("rus", "ru"),
+    ("swe", "sv"),
+    ("dan", "da"),
+    ("nor", "no"),
+    ("fin", "fi"),
+    ("pol", "pl"),
+    ("tur", "tr"),
+    ("tha", "th"),
+    ("vie", "vi"),
+    ("ind", "id"),
+    ("msa", "ms"),
+    ("may", "ms"),
+    ("ara", "ar"),
+    ("heb", "he"),
+    ("ell", "el"),
+    ("gre", "el"),
+    ("cat", "ca"),
+    ("lat", "la"),
+];
+
+impl Language {
+    /// Looks up a language name as displayed on a Kobo book page
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name
+            .trim()
+            .replace('（', "(")
+            .replace('）', ")")
+            .replace(" (", "(")
+            .to_lowercase();
+
+        LANGUAGE_NAMES
+            .iter()
+            .find(|language| {
+                language
+                    .names
+                    .iter()
+                    .any(|known_name| known_name.replace(" (", "(").to_lowercase() == name)
+            })
+            .map(|language| Self {
+                code: language.code.to_string(),
+                tag: language.tag.to_string(),
+            })
+    }
+
+    /// Parses a BCP 47 tag such as `zh-TW` or `eng`, e.g. from a `lang` attribute
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let mut subtags = tag.trim().split(['-', '_']);
+        let primary = subtags.next()?.to_lowercase();
+        let code = match primary.len() {
+            2 => primary,
+            3 => ISO_639_2_CODES
+                .iter()
+                .find(|(alpha_3, _)| *alpha_3 == primary)
+                .map(|(_, alpha_2)| alpha_2.to_string())?,
+            _ => return None,
+        };
+        if !code.chars().all(|char| char.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        let rest = subtags
+            .map(|subtag| subtag.to_lowercase())
+            .collect::<Vec<_>>();
+        let tag = match code.as_str() {
+            "zh" if rest
+                .iter()
+                .any(|subtag| ["hant", "tw", "hk", "mo"].contains(&subtag.as_str())) =>
+            {
+                "zh-Hant".to_string()
+            }
+            "zh" if rest
+                .iter()
+                .any(|subtag| ["hans", "cn", "sg"].contains(&subtag.as_str())) =>
+            {
+                "zh-Hans".to_string()
+            }
+            _ => code.clone(),
+        };
+
+        Some(Self { code, tag })
+    }
+}
+
+/// ISO 639-2 bibliographic code of an ISO 639-1 code, as used by MARC 21, e.g. `chi`
+pub fn to_marc_language_code(code: &str) -> Option<&'static str> {
+    ISO_639_2_CODES
+        .iter()
+        .rev()
+        .find(|(_, alpha_2)| *alpha_2 == code)
+        .map(|(alpha_3, _)| *alpha_3)
+}
+
+/// ISO 639-3 code of an ISO 639-1 code, as used by Calibre, e.g. `zho`
+pub fn to_iso_639_3_code(code: &str) -> Option<&'static str> {
+    ISO_639_2_CODES
+        .iter()
+        .find(|(_, alpha_2)| *alpha_2 == code)
+        .map(|(alpha_3, _)| *alpha_3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_language_has_alpha_3_codes() {
+        for language in LANGUAGE_NAMES {
+            let marc_code = to_marc_language_code(language.code);
+            let iso_639_3_code = to_iso_639_3_code(language.code);
+
+            assert!(marc_code.is_some(), "no MARC code for {}", language.code);
+            assert!(
+                iso_639_3_code.is_some(),
+                "no ISO 639-3 code for {}",
+                language.code
+            );
+            for alpha_3 in [marc_code, iso_639_3_code].into_iter().flatten() {
+                assert_eq!(
+                    Language::from_tag(alpha_3).map(|language| language.code),
+                    Some(language.code.to_string())
+                );
+            }
+        }
+        assert_eq!(to_marc_language_code("el"), Some("gre"));
+        assert_eq!(to_iso_639_3_code("el"), Some("ell"))
+    }
+
+    #[test]
+    fn language_from_name() {
+        let language = Language::from_name("韓文");
+
+        assert_eq!(
+            language,
+            Some(Language {
+                code: "ko".to_string(),
+                tag: "ko".to_string()
+            })
+        )
+    }
+
+    #[test]
+    fn language_from_name_with_script() {
+        let language = Language::from_name("Chinese （Simplified）");
+
+        assert_eq!(
+            language,
+            Some(Language {
+                code: "zh".to_string(),
+                tag: "zh-Hans".to_string()
+            })
+        )
+    }
+
+    #[test]
+    fn language_from_unknown_name() {
+        let language = Language::from_name("9781429989817");
+
+        assert_eq!(language, None)
+    }
+
+    #[test]
+    fn language_from_tag() {
+        let languages = ["zh-TW", "fre", "en-US"].map(Language::from_tag);
+
+        assert_eq!(
+            languages,
+            [("zh", "zh-Hant"), ("fr", "fr"), ("en", "en")].map(|(code, tag)| Some(Language {
+                code: code.to_string(),
+                tag: tag.to_string()
+            }))
+        )
+    }
+}
@@ -1,25 +1,300 @@
 mod book;
+mod calibre;
+mod catalogue;
+mod citation;
+mod column;
+mod config;
+mod crawler;
+mod csv_file;
+mod date;
+mod device;
+mod dublin_core;
+mod epub;
+mod filter;
+mod gallery;
+mod import;
+mod isbn;
+mod language;
+mod marc;
+mod name;
+mod onix;
+mod output;
+mod search;
+mod serve;
+mod synopsis;
+mod tag;
+mod template;
+mod tracker;
+mod xml;
 
-use anyhow::Result;
-use book::Id;
+use anyhow::{bail, Result};
+use book::{Id, Metadata, SCRAPE_STEPS};
+use catalogue::{open_catalogue, query_books, BookQuery, CATALOGUE_PATH};
+use clap::{Parser, Subcommand};
+use config::Config;
+use crawler::crawl_book_ids;
+use device::update_device;
+use epub::{read_accepted_book_ids, write_review_file, REVIEW_FILE_PATH};
+use filter::{BookFilter, BookKind};
+use gallery::{build_gallery, GALLERY_DIR};
+use import::read_book_ids;
 use indicatif::ProgressBar;
-use std::io::stdin;
+use isbn::Isbn;
+use output::{Output, CSV_FILE_PATH};
+use search::search_books;
+use serve::{serve, DEFAULT_ADDRESS};
+use std::{io::stdin, path::PathBuf, time::Duration};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    config: Config,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Download every book in the series of a Kobo book, sorted by series index
+    Series {
+        /// URL of any book in the series
+        url: String,
+    },
+    /// Download every book listed for an author
+    Author {
+        /// URL of a Kobo author page, or of a book whose first contributor is the author
+        url: String,
+
+        /// Kind of books to download
+        #[arg(long, value_enum, default_value_t)]
+        kind: BookKind,
+
+        #[command(flatten)]
+        filter: BookFilter,
+    },
+    /// Search Kobo by ISBN or "title author" and download the chosen book
+    Search {
+        query: String,
+
+        /// Maximum number of matches to show
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+    },
+    /// Download every book in a saved Kobo wishlist page or a Kobo data-export file
+    Import {
+        file: PathBuf,
+
+        #[command(flatten)]
+        filter: BookFilter,
+    },
+    /// Fill in the series, description, publisher and language of the sideloaded books
+    /// on a Kobo eReader, after backing up its database
+    Device {
+        /// Mount point of the eReader, or path of a KoboReader.sqlite file
+        path: PathBuf,
+
+        /// Number of Kobo search results to compare with each book
+        #[arg(long, default_value_t = 3)]
+        limit: usize,
+
+        /// Minimum confidence, from 0 to 1, for a match to be applied
+        #[arg(long, default_value_t = 0.8)]
+        threshold: f64,
+    },
+    /// List the books of the SQLite catalogue, e.g. every book in a series
+    Query {
+        #[command(flatten)]
+        query: BookQuery,
+
+        /// Catalogue written with `--format sqlite`
+        #[arg(long, default_value = CATALOGUE_PATH)]
+        catalogue: PathBuf,
+    },
+    /// Build a static HTML site from the CSV file and its covers, browsable without a server
+    Gallery {
+        /// CSV file written with the same column options as this run
+        #[arg(default_value = CSV_FILE_PATH)]
+        csv: PathBuf,
+
+        /// Folder to write the site into
+        #[arg(long, default_value = GALLERY_DIR)]
+        out: PathBuf,
+    },
+    /// Serve book metadata as JSON over HTTP: GET /books/{id}, /search?isbn= and /covers/{id}
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = DEFAULT_ADDRESS)]
+        address: String,
+
+        /// Seconds to keep fetched metadata and covers before scraping them again
+        #[arg(long, default_value_t = 3600)]
+        cache_ttl: u64,
+    },
+    /// Match a folder of EPUBs to Kobo books
+    Epub {
+        #[command(subcommand)]
+        command: EpubCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum EpubCommand {
+    /// Search Kobo for every EPUB in a folder and write the matches to ./epub-matches.csv for review
+    Match {
+        dir: PathBuf,
+
+        /// Number of Kobo search results to compare with each EPUB
+        #[arg(long, default_value_t = 3)]
+        limit: usize,
+
+        /// Minimum confidence, from 0 to 1, for a match to be accepted
+        #[arg(long, default_value_t = 0.8)]
+        threshold: f64,
+    },
+    /// Download the books accepted in a reviewed match file
+    Apply {
+        #[arg(default_value = REVIEW_FILE_PATH)]
+        review_file: PathBuf,
+    },
+}
 
 fn main() -> Result<()> {
-    println!("Enter Kobo book URLs:");
-    let book_ids = get_book_ids()?;
-
-    let pb = ProgressBar::new(book_ids.len() as u64);
-    for book_id in book_ids {
-        let book_pb = ProgressBar::new(15);
-        book_id
-            .get_metadata(&book_pb)?
-            .append_to_csv_file(&book_pb)?;
-        book_pb.finish_and_clear();
-        pb.inc(1);
+    let cli = Cli::parse();
+    let config = cli.config;
+
+    match cli.command {
+        None => {
+            println!("Enter Kobo book URLs:");
+            let book_ids = get_book_ids()?;
+
+            download_books(book_ids, &config, &BookFilter::default())?;
+        }
+        Some(Command::Series { url }) => {
+            let Some(book_id) = Id::from(url.as_str()) else {
+                bail!("Not a Kobo book URL!");
+            };
+            let book_ids = book_id.as_str().get_series_book_ids()?;
+
+            Output::run(&config, |output| {
+                let pb = ProgressBar::new(book_ids.len() as u64);
+                let mut books = Vec::<(Metadata, ProgressBar)>::new();
+                for book_id in book_ids {
+                    let book_pb = ProgressBar::new(SCRAPE_STEPS + output.steps());
+                    books.push((book_id.get_metadata(&book_pb)?, book_pb));
+                }
+                books.sort_by(|(a, _), (b, _)| {
+                    a.series_index()
+                        .unwrap_or(f64::MAX)
+                        .total_cmp(&b.series_index().unwrap_or(f64::MAX))
+                });
+
+                for (metadata, book_pb) in books {
+                    output.write(&metadata, &config, &book_pb)?;
+                    book_pb.finish_and_clear();
+                    pb.inc(1);
+                }
+
+                Ok(())
+            })?;
+        }
+        Some(Command::Author { url, kind, filter }) => {
+            let author_url = match Id::from(url.as_str()) {
+                Some(book_id) => book_id.as_str().get_author_url()?,
+                None => url,
+            };
+            let book_ids = crawl_book_ids(&author_url, kind)?;
+
+            download_books(book_ids, &config, &filter)?;
+        }
+        Some(Command::Search { query, limit }) => {
+            let books = search_books(&query, limit)?;
+            let Some(metadata) = pick_book(&query, books)? else {
+                bail!("No book picked!");
+            };
+
+            Output::run(&config, |output| {
+                let book_pb = ProgressBar::new(output.steps());
+                output.write(&metadata, &config, &book_pb)?;
+                book_pb.finish_and_clear();
+
+                Ok(())
+            })?;
+        }
+        Some(Command::Import { file, filter }) => {
+            let book_ids = read_book_ids(&file)?;
+            println!("Found {} books in {}", book_ids.len(), file.display());
+
+            download_books(book_ids, &config, &filter)?;
+        }
+        Some(Command::Device {
+            path,
+            limit,
+            threshold,
+        }) => {
+            update_device(&path, limit, threshold, &config)?;
+        }
+        Some(Command::Query { query, catalogue }) => {
+            let books = query_books(&open_catalogue(&catalogue)?, &query)?;
+            for book in &books {
+                println!("{}", book.describe());
+            }
+            println!("{} books", books.len());
+        }
+        Some(Command::Gallery { csv, out }) => {
+            let book_count = build_gallery(&csv, &out, &config)?;
+            println!(
+                "Wrote {} books to {}",
+                book_count,
+                out.join("index.html").display()
+            );
+        }
+        Some(Command::Serve { address, cache_ttl }) => {
+            serve(&address, Duration::from_secs(cache_ttl), &config)?;
+        }
+        Some(Command::Epub {
+            command:
+                EpubCommand::Match {
+                    dir,
+                    limit,
+                    threshold,
+                },
+        }) => {
+            write_review_file(&dir, limit, threshold)?;
+            println!(
+                "Review the matches in {}, then run `epub apply`",
+                REVIEW_FILE_PATH
+            );
+        }
+        Some(Command::Epub {
+            command: EpubCommand::Apply { review_file },
+        }) => {
+            let book_ids = read_accepted_book_ids(&review_file)?;
+
+            download_books(book_ids, &config, &BookFilter::default())?;
+        }
     }
 
-    Ok(println!("Done!"))
+    println!("Done!");
+    Ok(())
+}
+
+fn download_books(book_ids: Vec<String>, config: &Config, filter: &BookFilter) -> Result<()> {
+    Output::run(config, |output| {
+        let pb = ProgressBar::new(book_ids.len() as u64);
+        for book_id in book_ids {
+            let book_pb = ProgressBar::new(SCRAPE_STEPS + output.steps());
+            let metadata = book_id.get_metadata(&book_pb)?;
+            if metadata.matches(filter) {
+                output.write(&metadata, config, &book_pb)?;
+            }
+            book_pb.finish_and_clear();
+            pb.inc(1);
+        }
+
+        Ok(())
+    })
 }
 
 fn get_book_ids() -> Result<Vec<String>> {
@@ -43,3 +318,38 @@ fn get_book_ids() -> Result<Vec<String>> {
 
     Ok(book_ids)
 }
+
+fn pick_book(query: &str, mut books: Vec<Metadata>) -> Result<Option<Metadata>> {
+    if let Some(isbn) = Isbn::parse(query) {
+        if let Some(index) = books.iter().position(|book| book.has_isbn(&isbn)) {
+            return Ok(Some(books.swap_remove(index)));
+        }
+    }
+
+    if books.is_empty() {
+        println!("No books found!");
+        return Ok(None);
+    }
+
+    println!("Pick a book (leave empty to cancel):");
+    for (index, book) in books.iter().enumerate() {
+        println!("{}. {}", index + 1, book.describe());
+    }
+
+    loop {
+        let mut input = String::new();
+        stdin().read_line(&mut input)?;
+
+        let line = input.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        match line.parse::<usize>() {
+            Ok(number) if (1..=books.len()).contains(&number) => {
+                return Ok(Some(books.swap_remove(number - 1)));
+            }
+            _ => println!("Not a listed number!"),
+        }
+    }
+}
@@ -0,0 +1,478 @@
+use crate::{
+    book::Metadata,
+    config::Config,
+    filter::BookKind,
+    language::to_marc_language_code,
+    name::split_name,
+    output::AppendWriter,
+    synopsis::SynopsisFormat,
+    xml::{escape_xml, XmlCollectionWriter},
+};
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate};
+
+const MARC_FILE_PATH: &str = "./metadata.mrc";
+const MARCXML_FILE_PATH: &str = "./metadata.marcxml";
+const MARCXML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<collection xmlns=\"http://www.loc.gov/MARC21/slim\">\n";
+const MARCXML_FOOTER: &str = "</collection>\n";
+const FIELD_TERMINATOR: u8 = 0x1E;
+const RECORD_TERMINATOR: u8 = 0x1D;
+const SUBFIELD_DELIMITER: u8 = 0x1F;
+const LEADER_LENGTH: usize = 24;
+const DIRECTORY_ENTRY_LENGTH: usize = 12;
+/// Largest field and record, as the directory and leader hold their lengths in 4 and 5 digits
+const MAX_FIELD_LENGTH: usize = 9_999;
+const MAX_RECORD_LENGTH: usize = 99_999;
+/// Bytes of a data field with one subfield besides its value: indicators, subfield delimiter
+/// and code, and field terminator
+const DATA_FIELD_OVERHEAD: usize = 5;
+
+#[derive(Debug, PartialEq)]
+enum Field {
+    Control {
+        tag: &'static str,
+        value: String,
+    },
+    Data {
+        tag: &'static str,
+        indicators: [char; 2],
+        subfields: Vec<(char, String)>,
+    },
+}
+
+impl Field {
+    fn tag(&self) -> &'static str {
+        match self {
+            Field::Control { tag, .. } | Field::Data { tag, .. } => tag,
+        }
+    }
+
+    /// Field content in ISO 2709, with its field terminator
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::<u8>::new();
+        match self {
+            Field::Control { value, .. } => bytes.extend(value.as_bytes()),
+            Field::Data {
+                indicators,
+                subfields,
+                ..
+            } => {
+                bytes.extend(indicators.iter().collect::<String>().as_bytes());
+                for (code, value) in subfields {
+                    bytes.push(SUBFIELD_DELIMITER);
+                    bytes.extend(format!("{}{}", code, value).as_bytes());
+                }
+            }
+        }
+        bytes.push(FIELD_TERMINATOR);
+
+        bytes
+    }
+}
+
+/// A MARC 21 bibliographic record
+#[derive(Debug, PartialEq)]
+struct MarcRecord {
+    kind: BookKind,
+    fields: Vec<Field>,
+}
+
+impl MarcRecord {
+    fn from_metadata(metadata: &Metadata, config: &Config, date_entered: NaiveDate) -> Self {
+        let data = |tag, indicators, subfields: Vec<(char, String)>| Field::Data {
+            tag,
+            indicators,
+            subfields,
+        };
+        let name_field = |tag, name: &str, relator: &str| match split_name(name) {
+            Some((given_names, family_name)) => data(
+                tag,
+                ['1', ' '],
+                vec![
+                    ('a', format!("{}, {}", family_name, given_names)),
+                    ('e', relator.to_string()),
+                ],
+            ),
+            None => data(
+                tag,
+                ['0', ' '],
+                vec![('a', name.to_string()), ('e', relator.to_string())],
+            ),
+        };
+        let language = to_marc_language_code(metadata.language_code());
+        let authors = metadata.authors();
+
+        let mut fields = vec![
+            Field::Control {
+                tag: "001",
+                value: metadata.book_id(),
+            },
+            Field::Control {
+                tag: "008",
+                value: fixed_length_data(metadata, language, date_entered),
+            },
+        ];
+        if let Some(isbn) = metadata.isbn() {
+            fields.push(data("020", [' ', ' '], vec![('a', isbn.isbn_13.clone())]));
+            if let Some(isbn_10) = &isbn.isbn_10 {
+                fields.push(data("020", [' ', ' '], vec![('a', isbn_10.clone())]));
+            }
+        }
+        if let Some(language) = language {
+            fields.push(data("041", ['0', ' '], vec![('a', language.to_string())]));
+        }
+        if let Some(author) = authors.first() {
+            fields.push(name_field("100", author, "author"));
+        }
+
+        let mut title = vec![('a', metadata.title().to_string())];
+        if let Some(subtitle) = metadata.subtitle() {
+            title[0].1.push_str(" :");
+            title.push(('b', subtitle.to_string()));
+        }
+        let added_entry = if authors.is_empty() { '0' } else { '1' };
+        fields.push(data(
+            "245",
+            [added_entry, nonfiling_characters(metadata)],
+            title,
+        ));
+
+        let mut publication = Vec::<(char, String)>::new();
+        if !metadata.publisher().is_empty() {
+            publication.push(('b', metadata.publisher().to_string()));
+        }
+        if let Some(release_date) = metadata.release_date() {
+            publication.push(('c', release_date.year().to_string()));
+        }
+        if !publication.is_empty() {
+            fields.push(data("264", [' ', '1'], publication));
+        }
+
+        if let Some(series) = metadata.series_name() {
+            let mut series = vec![('a', series.to_string())];
+            if let Some(index) = metadata.series_index() {
+                series.push(('v', index.to_string()));
+            }
+            fields.push(data("490", ['0', ' '], series));
+        }
+
+        let summary = metadata
+            .synopsis(SynopsisFormat::Text)
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        // A long summary, e.g. 3-byte CJK characters, is split over repeated 520 fields
+        for summary in split_to_length(&summary, MAX_FIELD_LENGTH - DATA_FIELD_OVERHEAD) {
+            fields.push(data("520", [' ', ' '], vec![('a', summary)]));
+        }
+
+        for tag_path in metadata.get_tag_paths(config) {
+            let mut subjects = tag_path.into_iter();
+            let Some(topic) = subjects.next() else {
+                continue;
+            };
+            let mut subfields = vec![('a', topic)];
+            subfields.extend(subjects.map(|subdivision| ('x', subdivision)));
+            fields.push(data("650", [' ', '4'], subfields));
+        }
+
+        for author in authors.iter().skip(1) {
+            fields.push(name_field("700", author, "author"));
+        }
+        for narrator in metadata.narrators() {
+            fields.push(name_field("700", narrator, "narrator"));
+        }
+        fields.push(data("856", ['4', '0'], vec![('u', metadata.url())]));
+
+        Self {
+            kind: metadata.kind(),
+            fields,
+        }
+    }
+
+    fn leader(&self, record_length: usize, base_address: usize) -> String {
+        let record_type = match self.kind {
+            BookKind::Ebook => 'a',
+            BookKind::Audiobook => 'i',
+        };
+
+        format!(
+            "{:05}n{}m a22{:05}3i 4500",
+            record_length, record_type, base_address
+        )
+    }
+
+    /// Serializes the record in ISO 2709, the binary MARC 21 exchange format
+    fn to_iso2709(&self) -> Result<Vec<u8>> {
+        let mut directory = String::new();
+        let mut data = Vec::<u8>::new();
+        for field in &self.fields {
+            let bytes = field.to_bytes();
+            if bytes.len() > MAX_FIELD_LENGTH {
+                anyhow::bail!(
+                    "MARC field {} is {} bytes, over the limit of {}",
+                    field.tag(),
+                    bytes.len(),
+                    MAX_FIELD_LENGTH
+                );
+            }
+            directory.push_str(&format!(
+                "{}{:04}{:05}",
+                field.tag(),
+                bytes.len(),
+                data.len()
+            ));
+            data.extend(bytes);
+        }
+
+        let base_address = LEADER_LENGTH + self.fields.len() * DIRECTORY_ENTRY_LENGTH + 1;
+        let record_length = base_address + data.len() + 1;
+        if record_length > MAX_RECORD_LENGTH {
+            anyhow::bail!(
+                "The MARC record is {} bytes, over the limit of {}",
+                record_length,
+                MAX_RECORD_LENGTH
+            );
+        }
+        let mut record = self.leader(record_length, base_address).into_bytes();
+        record.extend(directory.as_bytes());
+        record.push(FIELD_TERMINATOR);
+        record.extend(data);
+        record.push(RECORD_TERMINATOR);
+
+        Ok(record)
+    }
+
+    fn to_marcxml(&self) -> Result<String> {
+        let iso2709_length = self.to_iso2709()?.len();
+        let base_address = LEADER_LENGTH + self.fields.len() * DIRECTORY_ENTRY_LENGTH + 1;
+        let mut lines = vec![
+            "  <record>".to_string(),
+            format!(
+                "    <leader>{}</leader>",
+                self.leader(iso2709_length, base_address)
+            ),
+        ];
+        for field in &self.fields {
+            match field {
+                Field::Control { tag, value } => lines.push(format!(
+                    r#"    <controlfield tag="{}">{}</controlfield>"#,
+                    tag,
+                    escape_xml(value)
+                )),
+                Field::Data {
+                    tag,
+                    indicators,
+                    subfields,
+                } => {
+                    lines.push(format!(
+                        r#"    <datafield tag="{}" ind1="{}" ind2="{}">"#,
+                        tag, indicators[0], indicators[1]
+                    ));
+                    for (code, value) in subfields {
+                        lines.push(format!(
+                            r#"      <subfield code="{}">{}</subfield>"#,
+                            code,
+                            escape_xml(value)
+                        ));
+                    }
+                    lines.push("    </datafield>".to_string());
+                }
+            }
+        }
+        lines.push("  </record>\n".to_string());
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Splits text into parts of at most `max_length` bytes, between words where it can
+fn split_to_length(text: &str, max_length: usize) -> Vec<String> {
+    let mut parts = Vec::<String>::new();
+    let mut part = String::new();
+    for char in text.chars() {
+        while part.len() + char.len_utf8() > max_length {
+            match part.rfind(' ').filter(|space| *space > 0) {
+                Some(space) => {
+                    let rest = part.split_off(space);
+                    parts.push(part);
+                    part = rest.trim_start().to_string();
+                }
+                None => parts.push(std::mem::take(&mut part)),
+            }
+        }
+        part.push(char);
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+
+    parts
+}
+
+/// Field 008, the 40 fixed-length character positions of a record
+fn fixed_length_data(
+    metadata: &Metadata,
+    language: Option<&str>,
+    date_entered: NaiveDate,
+) -> String {
+    let (date_type, year) = match metadata.release_date() {
+        Some(release_date) => ('s', format!("{:04}", release_date.year())),
+        None => ('n', "uuuu".to_string()),
+    };
+    // Positions 18-34 differ between books and sound recordings; both are online items
+    let material_specific = match metadata.kind() {
+        BookKind::Ebook => "    |o     000 | ",
+        BookKind::Audiobook => "    |o   ||||||| ",
+    };
+
+    format!(
+        "{}{}{}    xx {}{} d",
+        date_entered.format("%y%m%d"),
+        date_type,
+        year,
+        material_specific,
+        language.unwrap_or("und")
+    )
+}
+
+/// Count of leading characters to skip when sorting an English title, e.g. 4 for `The `
+fn nonfiling_characters(metadata: &Metadata) -> char {
+    if metadata.language_code() != "en" {
+        return '0';
+    }
+
+    ["The ", "An ", "A "]
+        .iter()
+        .find(|article| metadata.title().starts_with(*article))
+        .map_or('0', |article| {
+            char::from_digit(article.len() as u32, 10).unwrap_or('0')
+        })
+}
+
+/// Appends binary MARC 21 records to ./metadata.mrc
+pub fn marc_writer() -> AppendWriter {
+    AppendWriter::new(MARC_FILE_PATH, Vec::new(), |metadata, config| {
+        MarcRecord::from_metadata(metadata, config, Local::now().date_naive()).to_iso2709()
+    })
+}
+
+/// Adds MARCXML records to the collection in ./metadata.marcxml
+pub fn marcxml_writer() -> XmlCollectionWriter {
+    XmlCollectionWriter::new(
+        MARCXML_FILE_PATH,
+        MARCXML_HEADER.to_string(),
+        MARCXML_FOOTER,
+        |metadata, config| {
+            MarcRecord::from_metadata(metadata, config, Local::now().date_naive()).to_marcxml()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mistborn_record() -> MarcRecord {
+        MarcRecord::from_metadata(
+            &Metadata::sample(),
+            &Config::default(),
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn marc_fields() {
+        let record = mistborn_record();
+        let tags = record.fields.iter().map(Field::tag).collect::<Vec<&str>>();
+
+        assert_eq!(
+            tags,
+            vec![
+                "001", "008", "020", "020", "041", "100", "245", "264", "490", "520", "650", "856"
+            ]
+        );
+        assert_eq!(
+            record.fields[1],
+            Field::Control {
+                tag: "008",
+                value: "240601s2010    xx     |o     000 | eng d".to_string()
+            }
+        );
+        assert_eq!(
+            record.fields[6],
+            Field::Data {
+                tag: "245",
+                indicators: ['1', '0'],
+                subfields: vec![
+                    ('a', "Mistborn :".to_string()),
+                    ('b', "The Final Empire".to_string())
+                ]
+            }
+        )
+    }
+
+    #[test]
+    fn iso2709_record() -> Result<()> {
+        let record = mistborn_record().to_iso2709()?;
+        let leader = String::from_utf8_lossy(&record[..LEADER_LENGTH]).to_string();
+        let record_length = leader[..5].parse::<usize>().unwrap_or_default();
+        let base_address = leader[12..17].parse::<usize>().unwrap_or_default();
+
+        assert_eq!(record_length, record.len());
+        assert_eq!(record[base_address - 1], FIELD_TERMINATOR);
+        assert_eq!(&record[base_address..base_address + 11], b"mistborn-3\x1E");
+        assert_eq!(record.last(), Some(&RECORD_TERMINATOR));
+        Ok(())
+    }
+
+    #[test]
+    fn iso2709_long_synopsis() -> Result<()> {
+        let date_entered = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap_or_default();
+        let metadata = Metadata::sample().with_synopsis(&format!("<p>{}</p>", "迷".repeat(5_000)));
+        let record = MarcRecord::from_metadata(&metadata, &Config::default(), date_entered);
+        let iso2709 = record.to_iso2709()?;
+
+        // Every directory entry points at a field ending with a field terminator
+        let base_address = String::from_utf8_lossy(&iso2709[12..17]).parse::<usize>()?;
+        let directory = &iso2709[LEADER_LENGTH..base_address - 1];
+        for entry in directory.chunks(DIRECTORY_ENTRY_LENGTH) {
+            let entry = String::from_utf8_lossy(entry);
+            let length = entry[3..7].parse::<usize>()?;
+            let start = base_address + entry[7..12].parse::<usize>()?;
+            assert_eq!(iso2709[start + length - 1], FIELD_TERMINATOR);
+        }
+        assert_eq!(
+            record
+                .fields
+                .iter()
+                .filter(|field| field.tag() == "520")
+                .count(),
+            2
+        );
+
+        let metadata = Metadata::sample().with_synopsis(&"迷".repeat(40_000));
+        let record = MarcRecord::from_metadata(&metadata, &Config::default(), date_entered);
+        assert!(record.to_iso2709().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn marcxml_record() -> Result<()> {
+        let marcxml = format!(
+            "{}{}{}",
+            MARCXML_HEADER,
+            mistborn_record().to_marcxml()?,
+            MARCXML_FOOTER
+        );
+        let marcxml = roxmltree::Document::parse(&marcxml)?;
+        let isbn = marcxml
+            .descendants()
+            .find(|node| node.attribute("tag") == Some("020"))
+            .and_then(|datafield| datafield.first_element_child())
+            .and_then(|subfield| subfield.text());
+
+        assert_eq!(isbn, Some("9781429989817"));
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+/// Splits a personal name into given names and family name, e.g. `Brandon` and
+/// `Sanderson`. Single names and names in scripts written without spaces, such as CJK
+/// ones, have no split.
+pub fn split_name(name: &str) -> Option<(&str, &str)> {
+    let name = name.trim();
+    if name.chars().any(is_unspaced_script) {
+        return None;
+    }
+
+    name.rsplit_once(' ')
+        .map(|(given_names, family_name)| (given_names.trim(), family_name))
+}
+
+/// Whether a character belongs to a script that doesn't separate words with spaces, or to
+/// CJK punctuation and full-width forms
+fn is_unspaced_script(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0E00}'..='\u{0EFF}' // Thai, Lao
+            | '\u{1000}'..='\u{109F}' // Myanmar
+            | '\u{1100}'..='\u{11FF}' // Hangul Jamo
+            | '\u{1780}'..='\u{17FF}' // Khmer
+            | '\u{2E80}'..='\u{9FFF}' // CJK radicals, punctuation, kana and ideographs
+            | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+            | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+            | '\u{FF00}'..='\u{FFEF}' // Half-width and full-width forms
+            | '\u{20000}'..='\u{3FFFF}' // CJK ideograph extensions
+    )
+}
+
+/// Writes a name family name first, e.g. `Sanderson, Brandon`
+pub fn family_name_first(name: &str) -> String {
+    match split_name(name) {
+        Some((given_names, family_name)) => format!("{}, {}", family_name, given_names),
+        None => name.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_names() {
+        assert_eq!(family_name_first("Brandon Sanderson"), "Sanderson, Brandon");
+        assert_eq!(family_name_first("金浩然 （김호연）"), "金浩然 （김호연）");
+        assert_eq!(family_name_first("Plato"), "Plato")
+    }
+
+    #[test]
+    fn split_accented_names() {
+        assert_eq!(
+            split_name("Gabriel García Márquez"),
+            Some(("Gabriel García", "Márquez"))
+        );
+        assert_eq!(split_name("Émile Zola"), Some(("Émile", "Zola")));
+        assert_eq!(split_name("村上 春樹"), None)
+    }
+}
@@ -0,0 +1,532 @@
+use crate::{
+    book::Metadata,
+    config::Config,
+    filter::BookKind,
+    language::to_marc_language_code,
+    name::split_name,
+    synopsis::SynopsisFormat,
+    xml::{escape_xml, XmlCollectionWriter},
+};
+use chrono::Local;
+
+const ONIX_FILE_PATH: &str = "./metadata.onix.xml";
+const ONIX_FOOTER: &str = "</ONIXMessage>\n";
+
+/// An ONIX element, either holding text or other elements
+enum Element {
+    Text(&'static str, String),
+    Parent(&'static str, Vec<Element>),
+}
+
+use Element::{Parent, Text};
+
+impl Element {
+    fn code(name: &'static str, code: &str) -> Self {
+        Text(name, code.to_string())
+    }
+
+    fn to_xml(&self, depth: usize) -> String {
+        let indent = "  ".repeat(depth);
+        match self {
+            Text(name, text) => format!("{0}<{1}>{2}</{1}>\n", indent, name, escape_xml(text)),
+            Parent(name, children) => {
+                let children = children
+                    .iter()
+                    .map(|child| child.to_xml(depth + 1))
+                    .collect::<String>();
+                format!("{0}<{1}>\n{2}{0}</{1}>\n", indent, name, children)
+            }
+        }
+    }
+}
+
+/// Adds ONIX 3.0 `Product`s to the message in ./metadata.onix.xml
+pub fn onix_writer() -> XmlCollectionWriter {
+    XmlCollectionWriter::new(
+        ONIX_FILE_PATH,
+        onix_header(&Local::now().format("%Y%m%dT%H%M").to_string()),
+        ONIX_FOOTER,
+        |metadata, config| Ok(to_onix_product(metadata, config).to_xml(1)),
+    )
+}
+
+fn onix_header(sent_date_time: &str) -> String {
+    let header = Parent(
+        "Header",
+        vec![
+            Parent(
+                "Sender",
+                vec![Element::code("SenderName", env!("CARGO_PKG_NAME"))],
+            ),
+            Element::code("SentDateTime", sent_date_time),
+        ],
+    );
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ONIXMessage release=\"3.0\" xmlns=\"http://ns.editeur.org/onix/3.0/reference\">\n{}",
+        header.to_xml(1)
+    )
+}
+
+fn to_onix_product(metadata: &Metadata, config: &Config) -> Element {
+    let mut identifiers = vec![Parent(
+        "ProductIdentifier",
+        vec![
+            // 01: proprietary
+            Element::code("ProductIDType", "01"),
+            Element::code("IDTypeName", "Kobo"),
+            Element::code("IDValue", &metadata.book_id()),
+        ],
+    )];
+    if let Some(isbn) = metadata.isbn() {
+        identifiers.push(Parent(
+            "ProductIdentifier",
+            vec![
+                // 15: ISBN-13
+                Element::code("ProductIDType", "15"),
+                Element::code("IDValue", &isbn.isbn_13),
+            ],
+        ));
+    }
+
+    let mut product = vec![
+        Element::code("RecordReference", &metadata.url()),
+        // 03: notification confirmed on publication
+        Element::code("NotificationType", "03"),
+    ];
+    product.extend(identifiers);
+    product.push(descriptive_detail(metadata, config));
+    product.push(collateral_detail(metadata));
+    product.extend(publishing_detail(metadata));
+
+    Parent("Product", product)
+}
+
+/// Codes are from ONIX code lists 150 (form), 175 (form detail) and 17 (contributor role)
+fn descriptive_detail(metadata: &Metadata, config: &Config) -> Element {
+    let (product_form, product_form_detail) = match metadata.kind() {
+        // Digital download, EPUB
+        BookKind::Ebook => ("ED", "E101"),
+        // Downloadable audio file, MP3
+        BookKind::Audiobook => ("AJ", "A103"),
+    };
+    let mut detail = vec![
+        Element::code("ProductComposition", "00"),
+        Element::code("ProductForm", product_form),
+        Element::code("ProductFormDetail", product_form_detail),
+    ];
+
+    if let Some(series) = metadata.series_name() {
+        let mut title_element = vec![Element::code("TitleElementLevel", "02")];
+        if let Some(index) = metadata.series_index() {
+            title_element.push(Element::code("PartNumber", &index.to_string()));
+        }
+        title_element.push(Element::code("TitleText", series));
+        detail.push(Parent(
+            "Collection",
+            vec![
+                // 10: publisher collection
+                Element::code("CollectionType", "10"),
+                Parent(
+                    "TitleDetail",
+                    vec![
+                        Element::code("TitleType", "01"),
+                        Parent("TitleElement", title_element),
+                    ],
+                ),
+            ],
+        ));
+    }
+
+    let mut title_element = vec![
+        Element::code("TitleElementLevel", "01"),
+        Element::code("TitleText", metadata.title()),
+    ];
+    if let Some(subtitle) = metadata.subtitle() {
+        title_element.push(Element::code("Subtitle", subtitle));
+    }
+    detail.push(Parent(
+        "TitleDetail",
+        vec![
+            Element::code("TitleType", "01"),
+            Parent("TitleElement", title_element),
+        ],
+    ));
+
+    let contributors = metadata
+        .authors()
+        .into_iter()
+        .map(|author| (author, "A01"))
+        .chain(
+            metadata
+                .narrators()
+                .into_iter()
+                .map(|narrator| (narrator, "E07")),
+        );
+    for (sequence_number, (name, role)) in contributors.enumerate() {
+        let mut contributor = vec![
+            Element::code("SequenceNumber", &(sequence_number + 1).to_string()),
+            Element::code("ContributorRole", role),
+            Element::code("PersonName", name),
+        ];
+        if let Some((given_names, family_name)) = split_name(name) {
+            contributor.push(Element::code(
+                "PersonNameInverted",
+                &format!("{}, {}", family_name, given_names),
+            ));
+            contributor.push(Element::code("NamesBeforeKey", given_names));
+            contributor.push(Element::code("KeyNames", family_name));
+        }
+        detail.push(Parent("Contributor", contributor));
+    }
+
+    if let Some(language) = to_marc_language_code(metadata.language_code()) {
+        detail.push(Parent(
+            "Language",
+            vec![
+                // 01: language of text
+                Element::code("LanguageRole", "01"),
+                Element::code("LanguageCode", language),
+            ],
+        ));
+    }
+
+    // Content page count in pages, or duration in minutes
+    let extent = match (metadata.page_count(), metadata.duration()) {
+        (Some(page_count), _) => Some(("00", page_count, "03")),
+        (None, Some(duration)) => Some(("09", duration, "05")),
+        (None, None) => None,
+    };
+    if let Some((extent_type, value, unit)) = extent {
+        detail.push(Parent(
+            "Extent",
+            vec![
+                Element::code("ExtentType", extent_type),
+                Element::code("ExtentValue", &value.to_string()),
+                Element::code("ExtentUnit", unit),
+            ],
+        ));
+    }
+
+    for tag_path in metadata.get_tag_paths(config) {
+        detail.push(Parent(
+            "Subject",
+            vec![
+                // 24: proprietary subject scheme
+                Element::code("SubjectSchemeIdentifier", "24"),
+                Element::code("SubjectSchemeName", "Kobo"),
+                Element::code("SubjectHeadingText", &tag_path.join(" / ")),
+            ],
+        ));
+    }
+
+    Parent("DescriptiveDetail", detail)
+}
+
+fn collateral_detail(metadata: &Metadata) -> Element {
+    let mut detail = Vec::<Element>::new();
+    let synopsis = metadata.synopsis(SynopsisFormat::Text);
+    if !synopsis.is_empty() {
+        detail.push(Parent(
+            "TextContent",
+            vec![
+                // 03: description
+                Element::code("TextType", "03"),
+                Element::code("ContentAudience", "00"),
+                Element::code("Text", &synopsis),
+            ],
+        ));
+    }
+    detail.push(Parent(
+        "SupportingResource",
+        vec![
+            // 01: front cover image
+            Element::code("ResourceContentType", "01"),
+            Element::code("ContentAudience", "00"),
+            Element::code("ResourceMode", "03"),
+            Parent(
+                "ResourceVersion",
+                vec![
+                    // 02: downloadable file
+                    Element::code("ResourceForm", "02"),
+                    Element::code("ResourceLink", metadata.cover_url()),
+                ],
+            ),
+        ],
+    ));
+
+    Parent("CollateralDetail", detail)
+}
+
+/// Publishing details need a publisher, so books without one have none. Books released
+/// after today are forthcoming.
+fn publishing_detail(metadata: &Metadata) -> Option<Element> {
+    if metadata.publisher().is_empty() {
+        return None;
+    }
+
+    // 02: forthcoming, 04: active
+    let is_released = metadata
+        .release_date()
+        .is_none_or(|release_date| release_date <= Local::now().date_naive());
+    let publishing_status = if is_released { "04" } else { "02" };
+    let mut detail = vec![
+        Parent(
+            "Publisher",
+            vec![
+                // 01: publisher
+                Element::code("PublishingRole", "01"),
+                Element::code("PublisherName", metadata.publisher()),
+            ],
+        ),
+        Element::code("PublishingStatus", publishing_status),
+    ];
+    if let Some(release_date) = metadata.release_date() {
+        detail.push(Parent(
+            "PublishingDate",
+            vec![
+                // 01: publication date, as YYYYMMDD
+                Element::code("PublishingDateRole", "01"),
+                Element::code("Date", &release_date.format("%Y%m%d").to_string()),
+            ],
+        ));
+    }
+
+    Some(Parent("PublishingDetail", detail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use roxmltree::{Document, Node};
+
+    /// The children of the ONIX 3.0 composites this writer emits, in the order of the
+    /// reference schema, each marked when the schema requires it
+    const ONIX_STRUCTURE: &[(&str, &[(&str, bool)])] = &[
+        ("ONIXMessage", &[("Header", true), ("Product", false)]),
+        ("Header", &[("Sender", true), ("SentDateTime", true)]),
+        ("Sender", &[("SenderName", true)]),
+        (
+            "Product",
+            &[
+                ("RecordReference", true),
+                ("NotificationType", true),
+                ("ProductIdentifier", true),
+                ("DescriptiveDetail", false),
+                ("CollateralDetail", false),
+                ("PublishingDetail", false),
+            ],
+        ),
+        (
+            "ProductIdentifier",
+            &[
+                ("ProductIDType", true),
+                ("IDTypeName", false),
+                ("IDValue", true),
+            ],
+        ),
+        (
+            "DescriptiveDetail",
+            &[
+                ("ProductComposition", true),
+                ("ProductForm", true),
+                ("ProductFormDetail", false),
+                ("Collection", false),
+                ("TitleDetail", true),
+                ("Contributor", false),
+                ("Language", false),
+                ("Extent", false),
+                ("Subject", false),
+            ],
+        ),
+        (
+            "Collection",
+            &[("CollectionType", true), ("TitleDetail", false)],
+        ),
+        (
+            "TitleDetail",
+            &[("TitleType", true), ("TitleElement", true)],
+        ),
+        (
+            "TitleElement",
+            &[
+                ("TitleElementLevel", true),
+                ("PartNumber", false),
+                ("TitleText", false),
+                ("Subtitle", false),
+            ],
+        ),
+        (
+            "Contributor",
+            &[
+                ("SequenceNumber", false),
+                ("ContributorRole", true),
+                ("PersonName", false),
+                ("PersonNameInverted", false),
+                ("NamesBeforeKey", false),
+                ("KeyNames", false),
+            ],
+        ),
+        (
+            "Language",
+            &[("LanguageRole", true), ("LanguageCode", true)],
+        ),
+        (
+            "Extent",
+            &[
+                ("ExtentType", true),
+                ("ExtentValue", true),
+                ("ExtentUnit", true),
+            ],
+        ),
+        (
+            "Subject",
+            &[
+                ("SubjectSchemeIdentifier", true),
+                ("SubjectSchemeName", false),
+                ("SubjectHeadingText", false),
+            ],
+        ),
+        (
+            "CollateralDetail",
+            &[("TextContent", false), ("SupportingResource", false)],
+        ),
+        (
+            "TextContent",
+            &[
+                ("TextType", true),
+                ("ContentAudience", true),
+                ("Text", true),
+            ],
+        ),
+        (
+            "SupportingResource",
+            &[
+                ("ResourceContentType", true),
+                ("ContentAudience", true),
+                ("ResourceMode", true),
+                ("ResourceVersion", true),
+            ],
+        ),
+        (
+            "ResourceVersion",
+            &[("ResourceForm", true), ("ResourceLink", true)],
+        ),
+        (
+            "PublishingDetail",
+            &[
+                ("Publisher", false),
+                ("PublishingStatus", true),
+                ("PublishingDate", false),
+            ],
+        ),
+        (
+            "Publisher",
+            &[("PublishingRole", true), ("PublisherName", true)],
+        ),
+        (
+            "PublishingDate",
+            &[("PublishingDateRole", true), ("Date", true)],
+        ),
+    ];
+
+    /// Checks that every composite has its required children in schema order, and that
+    /// every other element has text
+    fn check_structure(element: Node) -> Result<()> {
+        let name = element.tag_name().name();
+        let children = element
+            .children()
+            .filter(Node::is_element)
+            .collect::<Vec<_>>();
+        let Some((_, expected)) = ONIX_STRUCTURE
+            .iter()
+            .find(|(composite, _)| *composite == name)
+        else {
+            if !children.is_empty() || element.text().is_none_or(|text| text.trim().is_empty()) {
+                anyhow::bail!("<{}> should hold text", name);
+            }
+            return Ok(());
+        };
+
+        let mut position = 0;
+        for child in &children {
+            let child_name = child.tag_name().name();
+            let Some(offset) = expected[position..]
+                .iter()
+                .position(|(expected, _)| *expected == child_name)
+            else {
+                anyhow::bail!("<{}> is out of place in <{}>", child_name, name);
+            };
+            position += offset;
+            check_structure(*child)?;
+        }
+        for (expected, required) in expected.iter() {
+            let is_present = children
+                .iter()
+                .any(|child| child.tag_name().name() == *expected);
+            if *required && !is_present {
+                anyhow::bail!("<{}> is missing <{}>", name, expected);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn onix_message(metadata: &Metadata) -> String {
+        format!(
+            "{}{}{}",
+            onix_header("20240601T0000"),
+            to_onix_product(metadata, &Config::default()).to_xml(1),
+            ONIX_FOOTER
+        )
+    }
+
+    #[test]
+    fn onix_products_are_valid() -> Result<()> {
+        let audiobook = Metadata::sample().with_kind(BookKind::Audiobook);
+        for metadata in [
+            Metadata::sample(),
+            Metadata::sample().with_publisher(""),
+            audiobook,
+        ] {
+            let message = onix_message(&metadata);
+            check_structure(Document::parse(&message)?.root_element())?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn onix_product_without_publisher() -> Result<()> {
+        let message = onix_message(&Metadata::sample().with_publisher(""));
+        let message = Document::parse(&message)?;
+
+        assert!(!message
+            .descendants()
+            .any(|node| node.has_tag_name("PublishingDetail")));
+        Ok(())
+    }
+
+    #[test]
+    fn onix_product() -> Result<()> {
+        let message = onix_message(&Metadata::sample());
+        let message = Document::parse(&message)?;
+        let text = |name: &str| {
+            message
+                .descendants()
+                .filter(|node| node.tag_name().name() == name)
+                .filter_map(|node| node.text())
+                .collect::<Vec<&str>>()
+        };
+
+        assert_eq!(text("IDValue"), vec!["mistborn-3", "9781429989817"]);
+        assert_eq!(text("TitleText"), vec!["Mistborn", "Mistborn"]);
+        assert_eq!(text("PartNumber"), vec!["1"]);
+        assert_eq!(text("KeyNames"), vec!["Sanderson"]);
+        assert_eq!(text("LanguageCode"), vec!["eng"]);
+        assert_eq!(text("PublishingStatus"), vec!["04"]);
+        assert_eq!(text("Date"), vec!["20100401"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,420 @@
+use crate::{
+    book::{Metadata, WRITE_STEPS},
+    calibre::{CalibreWriter, OpfWriter},
+    catalogue::CatalogueWriter,
+    citation::{bibtex_writer, ris_writer, CslJsonWriter},
+    config::{Config, OutputFormat},
+    csv_file::{
+        append_row, check_header, lock_csv_file, migrate_rows, read_header, strip_partial_row,
+        to_csv_row, write_atomically, CsvLock, HeaderCheck, UTF8_BOM,
+    },
+    dublin_core::dublin_core_writer,
+    marc::{marc_writer, marcxml_writer},
+    onix::onix_writer,
+    template::TemplateWriter,
+    tracker::{goodreads_writer, storygraph_writer},
+};
+use anyhow::Result;
+use indicatif::ProgressBar;
+use std::{
+    cell::RefCell,
+    fs::{read, File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+pub const CSV_FILE_PATH: &str = "./metadata.csv";
+const JSON_FILE_PATH: &str = "./metadata.jsonl";
+
+/// A scraped book on its way to the output writers, which share one download of its cover
+pub struct OutputBook<'a> {
+    pub metadata: &'a Metadata,
+    cover_path: RefCell<Option<String>>,
+}
+
+impl<'a> OutputBook<'a> {
+    pub fn new(metadata: &'a Metadata) -> Self {
+        Self {
+            metadata,
+            cover_path: RefCell::new(None),
+        }
+    }
+
+    /// Path of the cover in ./img, downloading it the first time it is asked for
+    pub fn cover_path(&self) -> Result<String> {
+        if let Some(cover_path) = self.cover_path.borrow().as_ref() {
+            return Ok(cover_path.clone());
+        }
+
+        let cover_path = self.metadata.download_cover()?;
+        *self.cover_path.borrow_mut() = Some(cover_path.clone());
+
+        Ok(cover_path)
+    }
+}
+
+/// Writes the books of a batch, e.g. a series, in one output format
+pub trait OutputWriter {
+    /// Prepares the output before the first book, e.g. by writing a header
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        Ok(())
+    }
+
+    /// Writes one book, advancing `pb` by `WRITE_STEPS`
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()>;
+
+    /// Completes the output after the last book
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Appends rows to ./metadata.csv, writing the header when the file is new. The file is
+/// locked for the batch, a row left unfinished by a crash is dropped and the header is
+/// checked first, and each row is synced on its own.
+#[derive(Default)]
+pub struct CsvWriter {
+    csv_file: Option<(CsvLock, File)>,
+}
+
+impl OutputWriter for CsvWriter {
+    fn begin(&mut self, config: &Config) -> Result<()> {
+        let path = Path::new(CSV_FILE_PATH);
+        let lock = lock_csv_file(path)?;
+        let expected = config.csv_columns();
+
+        let mut content = match path.exists() {
+            true => read(path)?,
+            false => Vec::new(),
+        };
+        if let Some(complete_rows) = strip_partial_row(&content) {
+            content = complete_rows.to_vec();
+            write_atomically(path, &content)?;
+            println!(
+                "Removed a partial row left in {} by an interrupted run",
+                path.display()
+            );
+        }
+        match read_header(&content, config.csv_delimiter)? {
+            None => {
+                let mut header = Vec::<u8>::new();
+                if config.csv_bom {
+                    header.extend(UTF8_BOM);
+                }
+                header.extend(to_csv_row(
+                    expected.iter().map(|(_, header)| header),
+                    config.csv_delimiter,
+                )?);
+                write_atomically(path, &header)?;
+            }
+            Some(existing) => {
+                if check_header(&existing, &expected)? == HeaderCheck::NeedsMigration {
+                    let migrated = migrate_rows(
+                        &content,
+                        config.csv_delimiter,
+                        &expected,
+                        &config.date_format,
+                    )?;
+                    write_atomically(path, &migrated)?;
+                    println!("Migrated {} to the current columns", path.display());
+                }
+            }
+        }
+        let csv_file = OpenOptions::new().append(true).open(path)?;
+        self.csv_file = Some((lock, csv_file));
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some((_, csv_file)) = self.csv_file.as_mut() else {
+            anyhow::bail!("The CSV writer was not begun");
+        };
+        let cover_path = book.cover_path()?;
+        pb.inc(1);
+
+        let row = to_csv_row(
+            config
+                .csv_columns()
+                .iter()
+                .map(|(column, _)| book.metadata.get_column_value(*column, &cover_path, config)),
+            config.csv_delimiter,
+        )?;
+        append_row(csv_file, &row)?;
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        // Dropping the lock releases the lock file
+        self.csv_file = None;
+
+        Ok(())
+    }
+}
+
+/// Appends JSON Lines to ./metadata.jsonl
+#[derive(Default)]
+pub struct JsonWriter {
+    json_file: Option<File>,
+}
+
+impl OutputWriter for JsonWriter {
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        self.json_file = Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(JSON_FILE_PATH)?,
+        );
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some(json_file) = self.json_file.as_mut() else {
+            anyhow::bail!("The JSON writer was not begun");
+        };
+        let cover_path = book.cover_path()?;
+        pb.inc(1);
+
+        writeln!(json_file, "{}", book.metadata.to_json(&cover_path, config))?;
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        self.json_file = None;
+
+        Ok(())
+    }
+}
+
+/// Appends the entries of a batch to a file in one write, for formats whose files are
+/// plain sequences of entries. A new file starts with `header`.
+pub struct AppendWriter {
+    path: &'static str,
+    header: Vec<u8>,
+    to_entry: fn(&Metadata, &Config) -> Result<Vec<u8>>,
+    file: Option<File>,
+    entries: Vec<u8>,
+}
+
+impl AppendWriter {
+    pub fn new(
+        path: &'static str,
+        header: Vec<u8>,
+        to_entry: fn(&Metadata, &Config) -> Result<Vec<u8>>,
+    ) -> Self {
+        Self {
+            path,
+            header,
+            to_entry,
+            file: None,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl OutputWriter for AppendWriter {
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path)?;
+        self.entries = match file.metadata()?.len() {
+            0 => self.header.clone(),
+            _ => Vec::new(),
+        };
+        self.file = Some(file);
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        if self.file.is_none() {
+            anyhow::bail!("The writer of {} was not begun", self.path);
+        }
+        let entry = (self.to_entry)(book.metadata, config)?;
+        pb.inc(1);
+
+        self.entries.extend(entry);
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.write_all(&self.entries)?;
+            file.sync_data()?;
+        }
+        self.entries.clear();
+
+        Ok(())
+    }
+}
+
+fn new_writer(format: OutputFormat) -> Box<dyn OutputWriter> {
+    match format {
+        OutputFormat::Csv => Box::<CsvWriter>::default(),
+        OutputFormat::Json => Box::<JsonWriter>::default(),
+        OutputFormat::Calibre => Box::<CalibreWriter>::default(),
+        OutputFormat::Opf => Box::new(OpfWriter),
+        OutputFormat::Sqlite => Box::<CatalogueWriter>::default(),
+        OutputFormat::Goodreads => Box::new(goodreads_writer()),
+        OutputFormat::Storygraph => Box::new(storygraph_writer()),
+        OutputFormat::Bibtex => Box::new(bibtex_writer()),
+        OutputFormat::Ris => Box::new(ris_writer()),
+        OutputFormat::CslJson => Box::<CslJsonWriter>::default(),
+        OutputFormat::Marc => Box::new(marc_writer()),
+        OutputFormat::Marcxml => Box::new(marcxml_writer()),
+        OutputFormat::DublinCore => Box::new(dublin_core_writer()),
+        OutputFormat::Onix => Box::new(onix_writer()),
+        OutputFormat::Template => Box::<TemplateWriter>::default(),
+    }
+}
+
+/// The formats given by --format, each once and in their first order
+fn unique_formats(formats: &[OutputFormat]) -> Vec<OutputFormat> {
+    let mut unique_formats = Vec::<OutputFormat>::new();
+    for format in formats {
+        if !unique_formats.contains(format) {
+            unique_formats.push(*format);
+        }
+    }
+
+    unique_formats
+}
+
+/// The writers of every format given by --format, run one after another on each book
+pub struct Output {
+    writers: Vec<Box<dyn OutputWriter>>,
+}
+
+impl Output {
+    /// Writes a batch in every format. The formats are finished even when writing fails
+    /// partway, so the books written before the error are kept in every format.
+    pub fn run(config: &Config, write_books: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        Self::begin(config)?.write_batch(config, write_books)
+    }
+
+    fn write_batch(
+        mut self,
+        config: &Config,
+        write_books: impl FnOnce(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        let result = write_books(&mut self);
+        let finished = self.finish(config);
+
+        result.and(finished)
+    }
+
+    /// Begins a batch in every format, ignoring repeated formats
+    fn begin(config: &Config) -> Result<Self> {
+        let mut writers = unique_formats(&config.format)
+            .into_iter()
+            .map(new_writer)
+            .collect::<Vec<_>>();
+        for writer in writers.iter_mut() {
+            writer.begin(config)?;
+        }
+
+        Ok(Self { writers })
+    }
+
+    /// Progress steps taken by writing one book
+    pub fn steps(&self) -> u64 {
+        WRITE_STEPS * self.writers.len() as u64
+    }
+
+    pub fn write(&mut self, metadata: &Metadata, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let book = OutputBook::new(metadata);
+        for writer in self.writers.iter_mut() {
+            writer.write(&book, config, pb)?;
+        }
+
+        Ok(())
+    }
+
+    /// Finishes every format, even after one of them fails, and returns the first error
+    fn finish(mut self, config: &Config) -> Result<()> {
+        let mut result = Ok(());
+        for writer in self.writers.iter_mut() {
+            let finished = writer.finish(config);
+            result = result.and(finished);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn repeated_formats() {
+        let formats = unique_formats(&[
+            OutputFormat::Bibtex,
+            OutputFormat::Ris,
+            OutputFormat::Bibtex,
+        ]);
+
+        assert_eq!(formats, vec![OutputFormat::Bibtex, OutputFormat::Ris])
+    }
+
+    /// Keeps the books it was given once finished
+    struct BatchWriter {
+        books: Vec<String>,
+        finished: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl OutputWriter for BatchWriter {
+        fn write(&mut self, book: &OutputBook, _config: &Config, pb: &ProgressBar) -> Result<()> {
+            self.books.push(book.metadata.book_id());
+            pb.inc(WRITE_STEPS);
+
+            Ok(())
+        }
+
+        fn finish(&mut self, _config: &Config) -> Result<()> {
+            self.finished.borrow_mut().append(&mut self.books);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn finish_after_error() {
+        let finished = Rc::new(RefCell::new(Vec::<String>::new()));
+        let output = Output {
+            writers: vec![Box::new(BatchWriter {
+                books: Vec::new(),
+                finished: finished.clone(),
+            })],
+        };
+        let config = Config::default();
+
+        let result = output.write_batch(&config, |output| {
+            output.write(&Metadata::sample(), &config, &ProgressBar::hidden())?;
+            anyhow::bail!("The next book couldn't be scraped")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(*finished.borrow(), vec![Metadata::sample().book_id()]);
+    }
+
+    #[test]
+    fn shared_cover_path() -> Result<()> {
+        let metadata = Metadata::sample();
+        let book = OutputBook::new(&metadata);
+        *book.cover_path.borrow_mut() = Some("./img/1.jpg".to_string());
+
+        assert_eq!(book.cover_path()?, "./img/1.jpg");
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{
+    book::{Id, Metadata},
+    crawler::ListPageHtml,
+    filter::BookKind,
+    isbn::Isbn,
+};
+use anyhow::Result;
+use indicatif::ProgressBar;
+use reqwest::Url;
+use scraper::Html;
+
+const SEARCH_PATH: &str = "https://www.kobo.com/tw/zh/search";
+
+/// Searches the Kobo store and scrapes the first `limit` matches
+pub fn search_books(query: &str, limit: usize) -> Result<Vec<Metadata>> {
+    let search_url = Url::parse_with_params(SEARCH_PATH, [("query", query)])?;
+    let response = reqwest::blocking::get(search_url)?;
+
+    // Kobo jumps straight to the book page when a search has a single exact match
+    let book_ids = match Id::from(response.url().as_str()) {
+        Some(book_id) => vec![book_id],
+        None => {
+            let search_page = Html::parse_document(&response.text()?);
+            search_page
+                .get_listed_book_ids(BookKind::Ebook)
+                .into_iter()
+                .take(limit)
+                .collect()
+        }
+    };
+
+    let pb = ProgressBar::new(book_ids.len() as u64);
+    let mut books = Vec::<Metadata>::new();
+    for book_id in book_ids {
+        books.push(book_id.get_metadata(&ProgressBar::hidden())?);
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    Ok(books)
+}
+
+/// Looks up the Kobo book with an ISBN, if Kobo knows it
+pub fn find_book_id_by_isbn(isbn: &Isbn) -> Result<Option<String>> {
+    let search_url = Url::parse_with_params(SEARCH_PATH, [("query", &isbn.isbn_13)])?;
+    let response = reqwest::blocking::get(search_url)?;
+
+    Ok(Id::from(response.url().as_str()))
+}
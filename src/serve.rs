@@ -0,0 +1,306 @@
+use crate::{
+    book::{Id, Metadata},
+    config::Config,
+    isbn::Isbn,
+    search::find_book_id_by_isbn,
+};
+use anyhow::{anyhow, Result};
+use indicatif::ProgressBar;
+use reqwest::{StatusCode, Url};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+const MAX_CACHED_BOOKS: usize = 1000;
+const MAX_CACHED_COVERS: usize = 100;
+
+#[derive(Debug, PartialEq)]
+enum Route {
+    /// `GET /books/{id}`, where audiobook IDs keep their `audiobook/` prefix
+    Book(String),
+    /// `GET /search?isbn=`
+    SearchIsbn(String),
+    /// `GET /covers/{id}`
+    Cover(String),
+    NotFound,
+}
+
+fn route(url: &str) -> Route {
+    let Ok(url) = Url::parse(&format!("http://localhost{}", url)) else {
+        return Route::NotFound;
+    };
+    let path = url.path();
+
+    if let Some(book_id) = path.strip_prefix("/books/").filter(|id| !id.is_empty()) {
+        return Route::Book(book_id.to_string());
+    }
+    if let Some(book_id) = path.strip_prefix("/covers/").filter(|id| !id.is_empty()) {
+        return Route::Cover(book_id.to_string());
+    }
+    if path == "/search" {
+        if let Some((_, isbn)) = url.query_pairs().find(|(name, _)| name == "isbn") {
+            return Route::SearchIsbn(isbn.to_string());
+        }
+    }
+
+    Route::NotFound
+}
+
+/// A book Kobo doesn't have, answered with 404 and never cached
+#[derive(Debug)]
+struct NotFound(&'static str);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// Status of the response to a failed request: 404 for books Kobo doesn't have, 502 for
+/// failed requests to Kobo and 500 for anything else
+fn error_status(error: &anyhow::Error) -> u16 {
+    if error.downcast_ref::<NotFound>().is_some() {
+        return 404;
+    }
+
+    match error
+        .chain()
+        .find_map(|error| error.downcast_ref::<reqwest::Error>())
+    {
+        Some(error) if error.status() == Some(StatusCode::NOT_FOUND) => 404,
+        Some(_) => 502,
+        None => 500,
+    }
+}
+
+/// Values fetched from Kobo, kept for a while so repeated requests don't scrape again.
+/// Expired entries are dropped before each insert, and the oldest ones past `capacity`.
+struct Cache<T> {
+    ttl: Duration,
+    capacity: usize,
+    entries: HashMap<String, (Instant, T)>,
+}
+
+impl<T: Clone> Cache<T> {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get_or_fetch(&mut self, key: &str, fetch: impl FnOnce() -> Result<T>) -> Result<T> {
+        if let Some((fetched_at, value)) = self.entries.get(key) {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(value.clone());
+            }
+        }
+
+        let value = fetch()?;
+        self.entries
+            .retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.ttl);
+        while self.entries.len() >= self.capacity {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (fetched_at, _))| *fetched_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.entries
+            .insert(key.to_string(), (Instant::now(), value.clone()));
+
+        Ok(value)
+    }
+}
+
+/// A JSON API over Kobo book pages, with covers proxied through it
+struct ApiServer<'a> {
+    config: &'a Config,
+    metadata: Cache<Metadata>,
+    isbns: Cache<String>,
+    covers: Cache<Vec<u8>>,
+}
+
+impl ApiServer<'_> {
+    fn get_metadata(&mut self, book_id: &str) -> Result<Metadata> {
+        self.metadata.get_or_fetch(book_id, || {
+            let metadata = book_id.get_metadata(&ProgressBar::hidden())?;
+            // Kobo answers some unknown IDs with a page that isn't a book page
+            if metadata.title().is_empty() {
+                return Err(NotFound("No book with this ID").into());
+            }
+
+            Ok(metadata)
+        })
+    }
+
+    /// Metadata as in the JSON output, with `cover_path` pointing at the cover proxy
+    fn to_json(&self, metadata: &Metadata) -> Value {
+        let book_id = metadata.book_id();
+
+        metadata.to_json(&format!("/covers/{}", book_id), self.config)
+    }
+
+    fn respond(&mut self, request: &Request) -> Result<Response<std::io::Cursor<Vec<u8>>>> {
+        if request.method() != &Method::Get {
+            return json_response(405, &json!({ "error": "Only GET is supported" }));
+        }
+
+        let response = match route(request.url()) {
+            Route::Book(book_id) => {
+                let metadata = self.get_metadata(&book_id)?;
+                json_response(200, &self.to_json(&metadata))
+            }
+            Route::SearchIsbn(isbn) => {
+                let Some(isbn) = Isbn::parse(&isbn) else {
+                    return json_response(400, &json!({ "error": "Not a valid ISBN" }));
+                };
+                let book_id = self.isbns.get_or_fetch(&isbn.isbn_13, || {
+                    find_book_id_by_isbn(&isbn)?
+                        .ok_or_else(|| NotFound("No book with this ISBN").into())
+                })?;
+                let metadata = self.get_metadata(&book_id)?;
+                json_response(200, &self.to_json(&metadata))
+            }
+            Route::Cover(book_id) => {
+                let metadata = self.get_metadata(&book_id)?;
+                let cover = self
+                    .covers
+                    .get_or_fetch(&book_id, || metadata.fetch_cover())?;
+                Ok(Response::from_data(cover).with_header(header("Content-Type", "image/jpeg")?))
+            }
+            Route::NotFound => json_response(404, &json!({ "error": "Not found" })),
+        }?;
+
+        Ok(response.with_header(self.cache_control()?))
+    }
+
+    fn cache_control(&self) -> Result<Header> {
+        header(
+            "Cache-Control",
+            &format!("max-age={}", self.metadata.ttl.as_secs()),
+        )
+    }
+}
+
+fn header(name: &str, value: &str) -> Result<Header> {
+    Header::from_bytes(name.as_bytes(), value.as_bytes())
+        .map_err(|_| anyhow!("Invalid header: {}: {}", name, value))
+}
+
+fn json_response(status: u16, body: &Value) -> Result<Response<std::io::Cursor<Vec<u8>>>> {
+    let response = Response::from_data(body.to_string().into_bytes())
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json")?);
+
+    Ok(response)
+}
+
+/// Serves the book metadata API until the process is stopped, one request at a time
+pub fn serve(address: &str, cache_ttl: Duration, config: &Config) -> Result<()> {
+    let server = Server::http(address).map_err(|error| anyhow!(error))?;
+    println!("Serving on http://{}", address);
+
+    let mut api = ApiServer {
+        config,
+        metadata: Cache::new(cache_ttl, MAX_CACHED_BOOKS),
+        isbns: Cache::new(cache_ttl, MAX_CACHED_BOOKS),
+        covers: Cache::new(cache_ttl, MAX_CACHED_COVERS),
+    };
+    for request in server.incoming_requests() {
+        let response = api.respond(&request).or_else(|error| {
+            let message = match error_status(&error) {
+                404 => error.to_string(),
+                502 => format!("Kobo request failed: {}", error),
+                _ => format!("Request failed: {}", error),
+            };
+            json_response(error_status(&error), &json!({ "error": message }))
+        })?;
+        println!(
+            "{} {} {}",
+            request.method(),
+            request.url(),
+            response.status_code().0
+        );
+        // A client that hung up shouldn't stop the server
+        if let Err(error) = request.respond(response) {
+            eprintln!("Failed to send the response: {}", error);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes() {
+        assert_eq!(
+            route("/books/mistborn-3"),
+            Route::Book("mistborn-3".to_string())
+        );
+        assert_eq!(
+            route("/covers/audiobook/mistborn-1"),
+            Route::Cover("audiobook/mistborn-1".to_string())
+        );
+        assert_eq!(
+            route("/search?isbn=978-1-4299-8981-7"),
+            Route::SearchIsbn("978-1-4299-8981-7".to_string())
+        );
+        assert_eq!(route("/search?q=mistborn"), Route::NotFound);
+        assert_eq!(route("/books/"), Route::NotFound)
+    }
+
+    #[test]
+    fn cache_expiry() -> Result<()> {
+        let mut fetches = 0;
+        let mut fetch = |cache: &mut Cache<u32>| {
+            cache.get_or_fetch("mistborn-3", || {
+                fetches += 1;
+                Ok(fetches)
+            })
+        };
+
+        let mut cache = Cache::new(Duration::from_secs(60), 10);
+        assert_eq!((fetch(&mut cache)?, fetch(&mut cache)?), (1, 1));
+        let mut expired_cache = Cache::new(Duration::ZERO, 10);
+        assert_eq!(
+            (fetch(&mut expired_cache)?, fetch(&mut expired_cache)?),
+            (2, 3)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cache_eviction() -> Result<()> {
+        let mut cache = Cache::new(Duration::from_secs(60), 2);
+        for book_id in ["mistborn-1", "mistborn-2", "mistborn-3"] {
+            cache.get_or_fetch(book_id, || Ok(book_id.to_string()))?;
+        }
+        assert!(cache
+            .get_or_fetch(
+                "mistborn-4",
+                || Err(NotFound("No book with this ID").into())
+            )
+            .is_err_and(|error| error_status(&error) == 404));
+
+        let mut book_ids = cache.entries.keys().cloned().collect::<Vec<String>>();
+        book_ids.sort();
+        assert_eq!(book_ids, ["mistborn-2", "mistborn-3"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use clap::ValueEnum;
+use ego_tree::NodeRef;
+use scraper::{Html, Node};
+
+const SAFE_TAGS: [&str; 18] = [
+    "p",
+    "br",
+    "strong",
+    "b",
+    "em",
+    "i",
+    "u",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+    "a",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+const BLOCK_TAGS: [&str; 12] = [
+    "p",
+    "div",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+const DROPPED_TAGS: [&str; 7] = [
+    "script", "style", "iframe", "object", "embed", "noscript", "template",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Default, ValueEnum)]
+pub enum SynopsisFormat {
+    /// Kobo's HTML, untouched
+    #[default]
+    Html,
+    /// HTML with only safe tags, no attributes but links, and no stray characters
+    SanitizedHtml,
+    Markdown,
+    Text,
+}
+
+impl SynopsisFormat {
+    pub fn label(self) -> &'static str {
+        match self {
+            SynopsisFormat::Html => "HTML",
+            SynopsisFormat::SanitizedHtml => "Sanitized HTML",
+            SynopsisFormat::Markdown => "Markdown",
+            SynopsisFormat::Text => "Plain Text",
+        }
+    }
+}
+
+pub fn format_synopsis(html: &str, format: SynopsisFormat) -> String {
+    let fragment = Html::parse_fragment(html);
+    let root = *fragment.root_element();
+
+    match format {
+        SynopsisFormat::Html => html.to_string(),
+        SynopsisFormat::SanitizedHtml => {
+            let mut sanitized_html = String::new();
+            root.children()
+                .for_each(|child| write_sanitized_html(child, &mut sanitized_html));
+
+            sanitized_html.trim().to_string()
+        }
+        SynopsisFormat::Markdown | SynopsisFormat::Text => {
+            let is_markdown = format == SynopsisFormat::Markdown;
+            let text = root
+                .children()
+                .map(|child| to_text(child, is_markdown))
+                .collect::<String>();
+
+            tidy_lines(&text)
+        }
+    }
+}
+
+fn write_sanitized_html(node: NodeRef<Node>, output: &mut String) {
+    match node.value() {
+        Node::Text(text) => {
+            let text = clean_text(text);
+            let is_blank_between_blocks = text.trim().is_empty()
+                && [node.prev_sibling(), node.next_sibling()]
+                    .iter()
+                    .flatten()
+                    .any(|sibling| is_block(*sibling));
+            if !is_blank_between_blocks {
+                output.push_str(
+                    &text
+                        .replace('&', "&amp;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;"),
+                );
+            }
+        }
+        Node::Element(element) if DROPPED_TAGS.contains(&element.name()) => {}
+        Node::Element(element) if SAFE_TAGS.contains(&element.name()) => {
+            let name = element.name();
+            let mut content = String::new();
+            node.children()
+                .for_each(|child| write_sanitized_html(child, &mut content));
+            if name != "br" && content.trim().is_empty() {
+                return;
+            }
+
+            match (name, element.attr("href").and_then(safe_url)) {
+                ("a", Some(href)) => {
+                    output.push_str(&format!("<a href=\"{}\">", href.replace('"', "&quot;")))
+                }
+                _ => output.push_str(&format!("<{}>", name)),
+            }
+            if name != "br" {
+                output.push_str(&content);
+                output.push_str(&format!("</{}>", name));
+            }
+        }
+        Node::Element(_) => node
+            .children()
+            .for_each(|child| write_sanitized_html(child, output)),
+        _ => {}
+    }
+}
+
+fn to_text(node: NodeRef<Node>, is_markdown: bool) -> String {
+    let children = || {
+        node.children()
+            .map(|child| to_text(child, is_markdown))
+            .collect::<String>()
+    };
+
+    match node.value() {
+        Node::Text(text) => {
+            let text = clean_text(text);
+            match is_markdown {
+                true => text
+                    .chars()
+                    .flat_map(|char| match "\\`*_[]".contains(char) {
+                        true => vec!['\\', char],
+                        false => vec![char],
+                    })
+                    .collect(),
+                false => text,
+            }
+        }
+        Node::Element(element) => match (element.name(), is_markdown) {
+            (name, _) if DROPPED_TAGS.contains(&name) => String::new(),
+            ("br", true) => "\\\n".to_string(),
+            ("br", false) => "\n".to_string(),
+            ("strong" | "b", true) => wrap_inline(&children(), "**"),
+            ("em" | "i", true) => wrap_inline(&children(), "*"),
+            ("a", true) => match element.attr("href").and_then(safe_url) {
+                Some(href) => format!("[{}]({})", children().trim(), href),
+                None => children(),
+            },
+            (heading @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6"), true) => {
+                let level = heading[1..].parse().unwrap_or(1);
+                format!("\n\n{} {}\n\n", "#".repeat(level), children().trim())
+            }
+            ("blockquote", true) => {
+                let quote = tidy_lines(&children())
+                    .lines()
+                    .map(|line| format!("> {}", line).trim_end().to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                format!("\n\n{}\n\n", quote)
+            }
+            ("li", _) => {
+                let is_ordered = node
+                    .parent()
+                    .and_then(|parent| parent.value().as_element())
+                    .is_some_and(|parent| parent.name() == "ol");
+                let marker = match is_ordered {
+                    true => {
+                        let index = node
+                            .prev_siblings()
+                            .filter(|sibling| sibling.value().is_element())
+                            .count();
+                        format!("{}.", index + 1)
+                    }
+                    false => "-".to_string(),
+                };
+                format!("\n{} {}", marker, children().trim())
+            }
+            ("ul" | "ol", _) => format!("\n\n{}\n\n", children()),
+            (name, _) if BLOCK_TAGS.contains(&name) => format!("\n\n{}\n\n", children()),
+            _ => children(),
+        },
+        _ => String::new(),
+    }
+}
+
+fn wrap_inline(text: &str, marker: &str) -> String {
+    match text.trim().is_empty() {
+        true => text.to_string(),
+        false => format!("{}{}{}", marker, text.trim(), marker),
+    }
+}
+
+/// Trims every line and collapses runs of blank lines into one
+fn tidy_lines(text: &str) -> String {
+    let mut lines = Vec::<&str>::new();
+    for line in text.lines().map(str::trim) {
+        let is_repeated_blank = line.is_empty() && lines.last().is_none_or(|last| last.is_empty());
+        if !is_repeated_blank {
+            lines.push(line);
+        }
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+/// Drops control and private-use characters and collapses whitespace
+fn clean_text(text: &str) -> String {
+    let text = text
+        .chars()
+        .filter(|char| !is_private_use(*char))
+        .map(|char| match char.is_control() {
+            true => ' ',
+            false => char,
+        })
+        .collect::<String>();
+    let mut cleaned_text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if text.starts_with(char::is_whitespace) && !cleaned_text.is_empty() {
+        cleaned_text.insert(0, ' ');
+    }
+    if text.ends_with(char::is_whitespace) {
+        cleaned_text.push(' ');
+    }
+
+    cleaned_text
+}
+
+fn is_private_use(char: char) -> bool {
+    matches!(char, '\u{e000}'..='\u{f8ff}' | '\u{f0000}'..='\u{ffffd}' | '\u{100000}'..='\u{10fffd}')
+}
+
+fn is_block(node: NodeRef<Node>) -> bool {
+    node.value()
+        .as_element()
+        .is_some_and(|element| BLOCK_TAGS.contains(&element.name()) || element.name() == "br")
+}
+
+fn safe_url(url: &str) -> Option<&str> {
+    let url = url.trim();
+    let is_safe = url.starts_with("https://") || url.starts_with("http://");
+
+    is_safe.then_some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYNOPSIS: &str = "<p><strong>艱難時刻的光亮之書</strong><br>\n<strong>一間便利店</strong></p>\n<p>\u{f0d8}</p>\n<p class=\"x\" data-track-info=\"{}\">譯有《剝削首爾》<a href=\"https://www.kobo.com\" onclick=\"track()\">等書</a>。</p><script>alert(1)</script>\n<ul><li>一</li><li>二</li></ul>";
+
+    #[test]
+    fn sanitize_synopsis() {
+        let synopsis = format_synopsis(SYNOPSIS, SynopsisFormat::SanitizedHtml);
+
+        assert_eq!(synopsis, "<p><strong>艱難時刻的光亮之書</strong><br><strong>一間便利店</strong></p><p>譯有《剝削首爾》<a href=\"https://www.kobo.com\">等書</a>。</p><ul><li>一</li><li>二</li></ul>")
+    }
+
+    #[test]
+    fn synopsis_to_markdown() {
+        let synopsis = format_synopsis(SYNOPSIS, SynopsisFormat::Markdown);
+
+        assert_eq!(synopsis, "**艱難時刻的光亮之書**\\\n**一間便利店**\n\n譯有《剝削首爾》[等書](https://www.kobo.com)。\n\n- 一\n- 二")
+    }
+
+    #[test]
+    fn synopsis_to_text() {
+        let synopsis = format_synopsis(SYNOPSIS, SynopsisFormat::Text);
+
+        assert_eq!(
+            synopsis,
+            "艱難時刻的光亮之書\n一間便利店\n\n譯有《剝削首爾》等書。\n\n- 一\n- 二"
+        )
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, fs::File, io::Read};
+
+/// Renames Kobo categories, read from a headerless CSV file of `from,to` rows.
+/// An empty `to` removes the category together with its subcategories.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TagMap(HashMap<String, String>);
+
+impl TagMap {
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|error| format!("{}: {}", path, error))?;
+
+        Self::from_reader(file).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self, csv::Error> {
+        let tag_map = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader)
+            .records()
+            .map(|record| {
+                let record = record?;
+                let from = record.get(0).unwrap_or_default().trim().to_string();
+                let to = record.get(1).unwrap_or_default().trim().to_string();
+
+                Ok((from, to))
+            })
+            .collect::<Result<HashMap<String, String>, csv::Error>>()?;
+
+        Ok(Self(tag_map))
+    }
+}
+
+/// Applies the tag map and, if asked, keeps only the leaf category of each path
+pub fn process_tag_paths(
+    tag_paths: &[Vec<String>],
+    tag_map: Option<&TagMap>,
+    leaf_only: bool,
+) -> Vec<Vec<String>> {
+    let mut processed_tag_paths = Vec::<Vec<String>>::new();
+    for tag_path in tag_paths {
+        let mut processed_tag_path = Vec::<String>::new();
+        for tag in tag_path {
+            let tag = match tag_map.and_then(|TagMap(tag_map)| tag_map.get(tag)) {
+                Some(renamed_tag) => renamed_tag.clone(),
+                None => tag.clone(),
+            };
+            if tag.is_empty() {
+                break;
+            }
+            processed_tag_path.push(tag);
+        }
+
+        if leaf_only {
+            processed_tag_path = processed_tag_path.split_off(processed_tag_path.len().max(1) - 1);
+        }
+        let is_new =
+            !processed_tag_path.is_empty() && !processed_tag_paths.contains(&processed_tag_path);
+        if is_new {
+            processed_tag_paths.push(processed_tag_path);
+        }
+    }
+
+    processed_tag_paths
+}
+
+/// Formats tag paths as Calibre hierarchical tags, e.g. `漫畫.圖像小說,幻想`.
+/// Commas inside category names become semicolons so they can't split a tag.
+pub fn to_calibre_tags(tag_paths: &[Vec<String>]) -> String {
+    join_tag_paths(tag_paths, ",")
+}
+
+/// Formats tag paths as hierarchical tags joined by `separator`. The separator inside
+/// category names becomes a semicolon, or a comma if the separator is a semicolon, and
+/// dots become middle dots so they can't add a level to the hierarchy.
+pub fn join_tag_paths(tag_paths: &[Vec<String>], separator: &str) -> String {
+    let substitute = if separator == ";" { "," } else { ";" };
+
+    tag_paths
+        .iter()
+        .map(|tag_path| {
+            tag_path
+                .iter()
+                .map(|tag| tag.replace(separator, substitute).replace('.', "·"))
+                .collect::<Vec<String>>()
+                .join(".")
+        })
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Checks --tag-separator, which can't be empty or the dot between levels of a tag
+pub fn parse_tag_separator(separator: &str) -> Result<String, String> {
+    match separator {
+        "" => Err("The tag separator can't be empty".to_string()),
+        "." => Err("The tag separator can't be the dot between tag levels".to_string()),
+        _ => Ok(separator.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag_paths(tag_paths: &[&[&str]]) -> Vec<Vec<String>> {
+        tag_paths
+            .iter()
+            .map(|tag_path| tag_path.iter().map(|tag| tag.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn map_tags() -> Result<(), csv::Error> {
+        let tag_map = TagMap::from_reader("漫畫、圖像小說與連環漫畫,漫畫\n兒童,\n".as_bytes())?;
+        let processed_tag_paths = process_tag_paths(
+            &tag_paths(&[
+                &["漫畫、圖像小說與連環漫畫", "幻想"],
+                &["兒童", "漫畫"],
+                &["青少年 - YA"],
+            ]),
+            Some(&tag_map),
+            false,
+        );
+
+        assert_eq!(
+            processed_tag_paths,
+            tag_paths(&[&["漫畫", "幻想"], &["青少年 - YA"]])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keep_leaf_tags() {
+        let processed_tag_paths = process_tag_paths(
+            &tag_paths(&[&["小說與文學", "幻想"], &["科幻小說與奇幻小說", "幻想"]]),
+            None,
+            true,
+        );
+
+        assert_eq!(processed_tag_paths, tag_paths(&[&["幻想"]]))
+    }
+
+    #[test]
+    fn format_calibre_tags() {
+        let calibre_tags = to_calibre_tags(&tag_paths(&[
+            &["Comics, Graphic Novels", "Manga"],
+            &["Fantasy"],
+            &["Sci-Fi", "Vol. 1"],
+        ]));
+
+        assert_eq!(
+            calibre_tags,
+            "Comics; Graphic Novels.Manga,Fantasy,Sci-Fi.Vol· 1"
+        );
+        assert!(parse_tag_separator("").is_err())
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    config::Config,
+    output::{OutputBook, OutputWriter},
+};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use minijinja::{context, Environment};
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default, ValueEnum)]
+pub enum TemplateMode {
+    /// Render one file per book, with the book as `book`
+    #[default]
+    Book,
+    /// Render one file for the whole batch, with the books as `books`
+    Batch,
+}
+
+/// Renders a Jinja-style template given by --template, e.g. a Markdown list for a blog or a
+/// Hugo front-matter file per book. Each book has the fields of the JSON output, including
+/// `cover_path`, and the `slug` filter makes any of them safe in --template-output.
+#[derive(Default)]
+pub struct TemplateWriter {
+    template: Option<Template>,
+    books: Vec<Value>,
+    /// Paths written in this batch, so that two books can't overwrite each other
+    paths: HashSet<PathBuf>,
+}
+
+/// The template and the output path template, parsed once per batch
+struct Template {
+    env: Environment<'static>,
+    name: String,
+}
+
+const OUTPUT_TEMPLATE_NAME: &str = "--template-output";
+
+impl Template {
+    fn from_config(config: &Config) -> Result<Self> {
+        let path = config
+            .template
+            .as_deref()
+            .ok_or_else(|| anyhow!("--format template needs --template"))?;
+        let output = config
+            .template_output
+            .clone()
+            .ok_or_else(|| anyhow!("--format template needs --template-output"))?;
+        // The extension of the name turns on HTML escaping for .html templates
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Self::new(name, read_to_string(path)?, output)
+    }
+
+    fn new(name: String, source: String, output: String) -> Result<Self> {
+        let mut env = Environment::new();
+        env.set_keep_trailing_newline(true);
+        env.add_filter("slug", slug);
+        env.add_template_owned(name.clone(), source)?;
+        env.add_template_owned(OUTPUT_TEMPLATE_NAME, output)?;
+
+        Ok(Self { env, name })
+    }
+
+    /// Renders the template and the output path with the same context
+    fn render(&self, context: minijinja::Value) -> Result<(PathBuf, String)> {
+        let content = self.env.get_template(&self.name)?.render(&context)?;
+        let path = self
+            .env
+            .get_template(OUTPUT_TEMPLATE_NAME)?
+            .render(&context)?;
+
+        Ok((PathBuf::from(path.trim()), content))
+    }
+}
+
+/// Lowercases the text and joins its words with `-`, e.g. `Mistborn: The Final Empire`
+/// becomes `mistborn-the-final-empire`
+fn slug(text: &str) -> String {
+    text.split(|char: char| !char.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+impl OutputWriter for TemplateWriter {
+    fn begin(&mut self, config: &Config) -> Result<()> {
+        self.template = Some(Template::from_config(config)?);
+        self.books.clear();
+        self.paths.clear();
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some(template) = &self.template else {
+            anyhow::bail!("The template writer was not begun");
+        };
+        let book = book.metadata.to_json(&book.cover_path()?, config);
+        pb.inc(1);
+
+        match config.template_mode {
+            TemplateMode::Book => {
+                let (path, content) = template.render(context! { book })?;
+                if !self.paths.insert(path.clone()) {
+                    anyhow::bail!(
+                        "Two books render to {}; make --template-output unique, e.g. with book.id",
+                        path.display()
+                    );
+                }
+                write_rendered_file(&path, &content)?;
+            }
+            TemplateMode::Batch => self.books.push(book),
+        }
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, config: &Config) -> Result<()> {
+        if let (Some(template), TemplateMode::Batch) = (&self.template, config.template_mode) {
+            let books = std::mem::take(&mut self.books);
+            let (path, content) = template.render(context! { books })?;
+            write_rendered_file(&path, &content)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a rendered file, which must stay inside the working directory
+fn write_rendered_file(path: &Path, content: &str) -> Result<()> {
+    let is_outside = path.components().any(|component| {
+        matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+    if path.as_os_str().is_empty() || is_outside {
+        anyhow::bail!(
+            "--template-output rendered {:?}, which is not a relative path inside the current folder",
+            path
+        );
+    }
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        create_dir_all(dir)?;
+    }
+    write(path, content)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::Metadata;
+
+    fn template(name: &str, source: &str, output: &str) -> Result<Template> {
+        Template::new(name.to_string(), source.to_string(), output.to_string())
+    }
+
+    #[test]
+    fn render_book() -> Result<()> {
+        let book = Metadata::sample().to_json("./img/1.jpg", &Config::default());
+        let (path, content) = template(
+            "book.md",
+            "---\ntitle: \"{{ book.title }}\"\nauthors: {{ book.authors | join(', ') }}\ncover: {{ book.cover_path }}\n---\n",
+            "content/books/{{ book.title | slug }}-{{ book.id }}.md",
+        )?
+        .render(context! { book })?;
+
+        assert_eq!(path, PathBuf::from("content/books/mistborn-mistborn-3.md"));
+        assert_eq!(
+            content,
+            "---\ntitle: \"Mistborn\"\nauthors: Brandon Sanderson\ncover: ./img/1.jpg\n---\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn render_batch_as_html() -> Result<()> {
+        let books = vec![Metadata::sample().to_json("./img/1.jpg", &Config::default())];
+        let (_, content) = template(
+            "books.html",
+            "{% for book in books %}<li>{{ book.publisher }} & {{ book.tags[0] | join(' > ') }}</li>{% endfor %}",
+            "books.html",
+        )?
+        .render(context! { books })?;
+
+        assert_eq!(
+            content,
+            "<li>Tor Publishing Group & Fiction &amp; Literature &gt; Fantasy</li>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reject_paths_outside() {
+        let rejected = ["/tmp/book.md", "../book.md", "books/../../book.md", ""]
+            .map(|path| write_rendered_file(Path::new(path), "").is_err());
+
+        assert_eq!(rejected, [true; 4])
+    }
+
+    #[test]
+    fn slug_titles() {
+        let slugs = ["Mistborn: The Final Empire", "../不便利的便利店 2"].map(slug);
+
+        assert_eq!(slugs, ["mistborn-the-final-empire", "不便利的便利店-2"])
+    }
+}
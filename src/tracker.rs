@@ -0,0 +1,252 @@
+use crate::{
+    book::Metadata, config::Config, filter::BookKind, name::family_name_first, output::AppendWriter,
+};
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate};
+
+const GOODREADS_FILE_PATH: &str = "./goodreads.csv";
+const STORYGRAPH_FILE_PATH: &str = "./storygraph.csv";
+const TO_READ_SHELF: &str = "to-read";
+/// Columns of a Goodreads library export, which Goodreads also imports
+const GOODREADS_HEADERS: [&str; 24] = [
+    "Book Id",
+    "Title",
+    "Author",
+    "Author l-f",
+    "Additional Authors",
+    "ISBN",
+    "ISBN13",
+    "My Rating",
+    "Average Rating",
+    "Publisher",
+    "Binding",
+    "Number of Pages",
+    "Year Published",
+    "Original Publication Year",
+    "Date Read",
+    "Date Added",
+    "Bookshelves",
+    "Bookshelves with positions",
+    "Exclusive Shelf",
+    "My Review",
+    "Spoiler",
+    "Private Notes",
+    "Read Count",
+    "Owned Copies",
+];
+/// Columns of a StoryGraph export, which StoryGraph also imports
+const STORYGRAPH_HEADERS: [&str; 23] = [
+    "Title",
+    "Authors",
+    "Contributors",
+    "ISBN/UID",
+    "Format",
+    "Read Status",
+    "Date Added",
+    "Last Date Read",
+    "Dates Read",
+    "Read Count",
+    "Moods",
+    "Pace",
+    "Character- or Plot-Driven?",
+    "Strong Character Development?",
+    "Loveable Characters?",
+    "Diverse Characters?",
+    "Flawed Characters?",
+    "Star Rating",
+    "Review",
+    "Content Warnings",
+    "Content Warning Description",
+    "Tags",
+    "Owned?",
+];
+
+/// Appends the books to ./goodreads.csv, shelved as to-read
+pub fn goodreads_writer() -> AppendWriter {
+    AppendWriter::new(
+        GOODREADS_FILE_PATH,
+        format!("{}\n", GOODREADS_HEADERS.join(",")).into_bytes(),
+        |metadata, _config| {
+            to_csv_record(&to_goodreads_record(metadata, Local::now().date_naive()))
+        },
+    )
+}
+
+/// Appends the books to ./storygraph.csv, marked as to-read
+pub fn storygraph_writer() -> AppendWriter {
+    AppendWriter::new(
+        STORYGRAPH_FILE_PATH,
+        format!("{}\n", STORYGRAPH_HEADERS.join(",")).into_bytes(),
+        |metadata, config| {
+            to_csv_record(&to_storygraph_record(
+                metadata,
+                config,
+                Local::now().date_naive(),
+            ))
+        },
+    )
+}
+
+fn to_csv_record<T: AsRef<[u8]>>(record: &[T]) -> Result<Vec<u8>> {
+    let mut csv_wtr = csv::Writer::from_writer(Vec::new());
+    csv_wtr.write_record(record)?;
+
+    Ok(csv_wtr.into_inner()?)
+}
+
+fn to_goodreads_record(metadata: &Metadata, date_added: NaiveDate) -> Vec<String> {
+    let authors = metadata.authors();
+    let author = authors.first().copied().unwrap_or_default();
+    let isbn = metadata.isbn();
+    let year_published = metadata
+        .release_date()
+        .map(|date| date.year().to_string())
+        .unwrap_or_default();
+    let binding = match metadata.kind() {
+        BookKind::Ebook => "Kobo eBook",
+        BookKind::Audiobook => "Audiobook",
+    };
+
+    vec![
+        String::new(),
+        metadata.title().to_string(),
+        author.to_string(),
+        family_name_first(author),
+        authors.get(1..).unwrap_or_default().join(", "),
+        isbn.and_then(|isbn| isbn.isbn_10.clone())
+            .unwrap_or_default(),
+        isbn.map(|isbn| isbn.isbn_13.clone()).unwrap_or_default(),
+        "0".to_string(),
+        String::new(),
+        metadata.publisher().to_string(),
+        binding.to_string(),
+        metadata
+            .page_count()
+            .map(|pages| pages.to_string())
+            .unwrap_or_default(),
+        year_published.clone(),
+        year_published,
+        String::new(),
+        date_added.format("%Y/%m/%d").to_string(),
+        TO_READ_SHELF.to_string(),
+        format!("{} (#1)", TO_READ_SHELF),
+        TO_READ_SHELF.to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        "0".to_string(),
+        "0".to_string(),
+    ]
+}
+
+fn to_storygraph_record(
+    metadata: &Metadata,
+    config: &Config,
+    date_added: NaiveDate,
+) -> Vec<String> {
+    let isbn = metadata.isbn().map(|isbn| isbn.isbn_13.clone());
+    let format = match metadata.kind() {
+        BookKind::Ebook => "digital",
+        BookKind::Audiobook => "audio",
+    };
+    let tags = metadata
+        .get_tag_paths(config)
+        .iter()
+        .filter_map(|tag_path| tag_path.last())
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .join(", ");
+
+    vec![
+        metadata.title().to_string(),
+        metadata.authors().join(", "),
+        metadata
+            .narrators()
+            .iter()
+            .map(|narrator| format!("{} (Narrator)", narrator))
+            .collect::<Vec<String>>()
+            .join(", "),
+        isbn.unwrap_or_else(|| metadata.book_id()),
+        format.to_string(),
+        TO_READ_SHELF.to_string(),
+        date_added.format("%Y/%m/%d").to_string(),
+        String::new(),
+        String::new(),
+        "0".to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        tags,
+        String::new(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goodreads_record() {
+        let record = to_goodreads_record(
+            &Metadata::sample(),
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap_or_default(),
+        );
+        let columns = GOODREADS_HEADERS
+            .iter()
+            .zip(record.iter().map(String::as_str))
+            .filter(|(_, value)| !value.is_empty() && *value != "0")
+            .collect::<Vec<(&&str, &str)>>();
+
+        assert_eq!(
+            columns,
+            vec![
+                (&"Title", "Mistborn"),
+                (&"Author", "Brandon Sanderson"),
+                (&"Author l-f", "Sanderson, Brandon"),
+                (&"ISBN", "1429989815"),
+                (&"ISBN13", "9781429989817"),
+                (&"Publisher", "Tor Publishing Group"),
+                (&"Binding", "Kobo eBook"),
+                (&"Number of Pages", "672"),
+                (&"Year Published", "2010"),
+                (&"Original Publication Year", "2010"),
+                (&"Date Added", "2024/06/01"),
+                (&"Bookshelves", "to-read"),
+                (&"Bookshelves with positions", "to-read (#1)"),
+                (&"Exclusive Shelf", "to-read"),
+            ]
+        )
+    }
+
+    #[test]
+    fn storygraph_record() {
+        let record = to_storygraph_record(
+            &Metadata::sample(),
+            &Config::default(),
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap_or_default(),
+        );
+
+        assert_eq!(record.len(), STORYGRAPH_HEADERS.len());
+        assert_eq!(
+            record[..7],
+            [
+                "Mistborn",
+                "Brandon Sanderson",
+                "",
+                "9781429989817",
+                "digital",
+                "to-read",
+                "2024/06/01"
+            ]
+        );
+        assert_eq!(record[21], "Fantasy")
+    }
+}
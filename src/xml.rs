@@ -0,0 +1,127 @@
+use crate::{
+    book::Metadata,
+    config::Config,
+    csv_file::write_atomically,
+    output::{OutputBook, OutputWriter},
+};
+use anyhow::Result;
+use indicatif::ProgressBar;
+use std::{fs::read_to_string, path::Path};
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Adds the records of a batch to an XML file whose root element collects records,
+/// reading the file once in `begin` and writing it once in `finish`. A new file gets
+/// `header` and `footer` around the records.
+pub struct XmlCollectionWriter {
+    path: &'static str,
+    header: String,
+    footer: &'static str,
+    to_record: fn(&Metadata, &Config) -> Result<String>,
+    /// The collection so far, without its footer
+    records: Option<String>,
+}
+
+impl XmlCollectionWriter {
+    pub fn new(
+        path: &'static str,
+        header: String,
+        footer: &'static str,
+        to_record: fn(&Metadata, &Config) -> Result<String>,
+    ) -> Self {
+        Self {
+            path,
+            header,
+            footer,
+            to_record,
+            records: None,
+        }
+    }
+}
+
+impl OutputWriter for XmlCollectionWriter {
+    fn begin(&mut self, _config: &Config) -> Result<()> {
+        let path = Path::new(self.path);
+        let records = match path.exists() {
+            true => {
+                let content = read_to_string(path)?;
+                let Some(records) = content.trim_end().strip_suffix(self.footer.trim()) else {
+                    anyhow::bail!("{}: not closed by {}", path.display(), self.footer.trim());
+                };
+                records.to_string()
+            }
+            false => self.header.clone(),
+        };
+        self.records = Some(records);
+
+        Ok(())
+    }
+
+    fn write(&mut self, book: &OutputBook, config: &Config, pb: &ProgressBar) -> Result<()> {
+        let Some(records) = self.records.as_mut() else {
+            anyhow::bail!("The writer of {} was not begun", self.path);
+        };
+        let record = (self.to_record)(book.metadata, config)?;
+        pb.inc(1);
+
+        records.push_str(&record);
+        pb.inc(1);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, _config: &Config) -> Result<()> {
+        if let Some(records) = self.records.take() {
+            write_atomically(
+                Path::new(self.path),
+                format!("{}{}", records, self.footer).as_bytes(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::remove_file};
+
+    #[test]
+    fn write_xml_collection() -> Result<()> {
+        let path = temp_dir().join("kobo-books-metadata-downloader-collection.xml");
+        let _ = remove_file(&path);
+        let path: &'static str = Box::leak(path.to_string_lossy().into_owned().into_boxed_str());
+        let mut writer = XmlCollectionWriter::new(
+            path,
+            "<records>\n".to_string(),
+            "</records>\n",
+            |metadata, _config| Ok(format!("  <record>{}</record>\n", metadata.id())),
+        );
+        let (metadata, config, pb) = (Metadata::sample(), Config::default(), ProgressBar::hidden());
+        let book = OutputBook::new(&metadata);
+
+        for books in [2, 1] {
+            writer.begin(&config)?;
+            for _ in 0..books {
+                writer.write(&book, &config, &pb)?;
+            }
+            writer.finish(&config)?;
+        }
+
+        let content = read_to_string(path)?;
+        remove_file(path)?;
+        assert_eq!(
+            content,
+            "<records>\n".to_string()
+                + &"  <record>mistborn-3</record>\n".repeat(3)
+                + "</records>\n"
+        );
+        Ok(())
+    }
+}